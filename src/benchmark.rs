@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use servo::servo_url::ServoUrl;
+
+// Loads that take longer than this are recorded without a load end time
+const LOAD_TIMEOUT_S: u64 = 60;
+
+struct Run {
+    index: usize,
+    started: Instant,
+    // Same logic as the splash screen: the first frame presented after the
+    // head is parsed is the first paint
+    content_expected: bool,
+    first_paint: Option<Duration>,
+}
+
+struct Sample {
+    url: ServoUrl,
+    iteration: u32,
+    first_paint: Option<Duration>,
+    load_end: Option<Duration>,
+    // The network error, for loads that failed
+    error: Option<String>,
+}
+
+// Loads each URL of a list `warmup + repeat` times in a row, then writes the
+// timings of the non-warmup loads as CSV and finishes. The first load is the
+// startup one, so it includes servo's own startup.
+pub struct Benchmark {
+    urls: Vec<ServoUrl>,
+    warmup: u32,
+    repeat: u32,
+    output: Option<String>,
+    // Both are called from servo's callbacks and must defer their work
+    load: Box<Fn(ServoUrl)>,
+    finish: Box<Fn()>,
+    run: RefCell<Option<Run>>,
+    samples: RefCell<Vec<Sample>>,
+}
+
+impl Benchmark {
+    pub fn new(urls: Vec<ServoUrl>, warmup: u32, repeat: u32, output: Option<String>,
+               load: Box<Fn(ServoUrl)>, finish: Box<Fn()>) -> Benchmark {
+        Benchmark {
+            urls,
+            warmup,
+            repeat,
+            output,
+            load,
+            finish,
+            run: RefCell::new(None),
+            samples: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn first_url(&self) -> ServoUrl {
+        self.urls[0].clone()
+    }
+
+    // Right before the first URL is loaded
+    pub fn start(&self) {
+        self.start_run(0);
+    }
+
+    pub fn head_parsed(&self) {
+        if let Some(ref mut run) = *self.run.borrow_mut() {
+            run.content_expected = true;
+        }
+    }
+
+    pub fn frame_presented(&self) {
+        if let Some(ref mut run) = *self.run.borrow_mut() {
+            if run.content_expected && run.first_paint.is_none() {
+                run.first_paint = Some(run.started.elapsed());
+            }
+        }
+    }
+
+    pub fn load_ended(&self) {
+        let load_end = match *self.run.borrow() {
+            Some(ref run) => run.started.elapsed(),
+            None => return,
+        };
+        self.finish_run(Some(load_end), None);
+    }
+
+    // The run is over, without a load end time
+    pub fn load_failed(&self, error: &str) {
+        eprintln!("Load failed: {}", error);
+        self.finish_run(None, Some(error.to_owned()));
+    }
+
+    // Called periodically, so a page that never finishes loading doesn't
+    // stall the whole benchmark
+    pub fn check_timeout(&self) {
+        let timed_out = match *self.run.borrow() {
+            Some(ref run) => run.started.elapsed() > Duration::from_secs(LOAD_TIMEOUT_S),
+            None => false,
+        };
+        if timed_out {
+            eprintln!("Load timed out after {}s", LOAD_TIMEOUT_S);
+            self.finish_run(None, None);
+        }
+    }
+
+    fn runs_per_url(&self) -> usize {
+        (self.warmup + self.repeat) as usize
+    }
+
+    fn start_run(&self, index: usize) {
+        *self.run.borrow_mut() = Some(Run {
+            index,
+            started: Instant::now(),
+            content_expected: false,
+            first_paint: None,
+        });
+    }
+
+    fn finish_run(&self, load_end: Option<Duration>, error: Option<String>) {
+        let run = match self.run.borrow_mut().take() {
+            Some(run) => run,
+            None => return,
+        };
+        let iteration = (run.index % self.runs_per_url()) as u32;
+        if iteration >= self.warmup {
+            self.samples.borrow_mut().push(Sample {
+                url: self.urls[run.index / self.runs_per_url()].clone(),
+                iteration: iteration - self.warmup + 1,
+                first_paint: run.first_paint,
+                load_end,
+                error,
+            });
+        }
+
+        let index = run.index + 1;
+        if index < self.urls.len() * self.runs_per_url() {
+            self.start_run(index);
+            (self.load)(self.urls[index / self.runs_per_url()].clone());
+        } else {
+            if let Err(error) = self.write_report() {
+                eprintln!("Can't write the benchmark report: {}", error);
+            }
+            (self.finish)();
+        }
+    }
+
+    fn write_report(&self) -> io::Result<()> {
+        // Missing timings and errors are left empty
+        let mut report = String::from("url,iteration,first_paint_ms,load_end_ms,error\n");
+        for sample in self.samples.borrow().iter() {
+            report.push_str(&format!("{},{},{},{},{}\n",
+                                     sample.url,
+                                     sample.iteration,
+                                     sample.first_paint.map(millis).unwrap_or_default(),
+                                     sample.load_end.map(millis).unwrap_or_default(),
+                                     sample.error.as_ref().map_or("", |error| error.as_str())));
+        }
+        match self.output {
+            Some(ref path) => File::create(path)?.write_all(report.as_bytes()),
+            None => {
+                print!("{}", report);
+                Ok(())
+            },
+        }
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    let millis = duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0;
+    format!("{:.1}", millis)
+}
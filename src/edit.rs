@@ -0,0 +1,69 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use gtk::{self, Continue};
+use servo::Servo;
+use servo::compositing::windowing::WindowEvent;
+use servo::servo_url::ServoUrl;
+
+use ServoWindow;
+
+const WATCH_INTERVAL_MS: u32 = 500;
+
+// Only file:// pages can be edited
+pub fn local_path(url: &ServoUrl) -> Option<PathBuf> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.as_url().to_file_path().ok()
+}
+
+// Uses the configured command, then $VISUAL or $EDITOR. The desktop's
+// handler for the file isn't a fallback, for HTML it's usually a browser.
+pub fn open_in_editor(editor: Option<&str>, path: &Path) {
+    let command = editor.map(|editor| editor.to_owned())
+        .or_else(|| env::var("VISUAL").ok())
+        .or_else(|| env::var("EDITOR").ok());
+    let command = match command {
+        Some(command) => command,
+        None => {
+            eprintln!("No editor configured, use --editor or set $VISUAL or $EDITOR");
+            return;
+        },
+    };
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => return,
+    };
+    if let Err(error) = Command::new(program).args(words).arg(path).spawn() {
+        eprintln!("Can't run {}: {}", command, error);
+    }
+}
+
+// Reloads the selected page whenever the local file it was loaded from is modified
+pub fn reload_on_change(servo: Rc<RefCell<Servo<ServoWindow>>>, window: Rc<ServoWindow>) {
+    let last_seen: RefCell<Option<(PathBuf, SystemTime)>> = RefCell::new(None);
+    gtk::timeout_add(WATCH_INTERVAL_MS, move || {
+        let seen = window.current_url()
+            .and_then(|url| local_path(&url))
+            .and_then(|path| {
+                let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+                modified.map(|modified| (path, modified))
+            });
+        let changed = match (&*last_seen.borrow(), &seen) {
+            (&Some((ref old_path, old_time)), &Some((ref path, time))) => old_path == path && old_time != time,
+            _ => false,
+        };
+        if let (true, Some(browser_id)) = (changed, window.selected_browser()) {
+            servo.borrow_mut().handle_events(vec![WindowEvent::Reload(browser_id)]);
+        }
+        *last_seen.borrow_mut() = seen;
+        Continue(true)
+    });
+}
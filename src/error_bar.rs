@@ -0,0 +1,115 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gtk::{
+    Align,
+    BoxExt,
+    Cast,
+    InfoBar,
+    InfoBarExt,
+    Label,
+    LabelExt,
+    MessageType,
+    ResponseType,
+    WidgetExt,
+};
+use gtk::Orientation::Vertical;
+
+// Errors reported while the bar is shown and within this delay (in seconds) of
+// the previous one are only counted, so a page spamming errors doesn't flicker
+const RATE_LIMIT: u64 = 5;
+
+// A dismissible bar at the top of the page summarizing the last page error
+pub struct ErrorBar {
+    info_bar: InfoBar,
+    summary: Label,
+    details: Label,
+    summary_text: RefCell<String>,
+    last_report: Cell<Option<Instant>>,
+    suppressed: Cell<u32>,
+}
+
+impl ErrorBar {
+    pub fn new() -> Rc<ErrorBar> {
+        let info_bar = InfoBar::new();
+        info_bar.set_message_type(MessageType::Error);
+        info_bar.set_show_close_button(true);
+        info_bar.set_no_show_all(true);
+        let details_response: i32 = ResponseType::Help.into();
+        info_bar.add_button("Details", details_response);
+
+        let summary = Label::new(None);
+        summary.set_halign(Align::Start);
+        summary.set_line_wrap(true);
+        summary.show();
+        let details = Label::new(None);
+        details.set_halign(Align::Start);
+        details.set_line_wrap(true);
+        details.set_selectable(true);
+        details.set_no_show_all(true);
+
+        let labels = gtk::Box::new(Vertical, 4);
+        labels.pack_start(&summary, false, false, 0);
+        labels.pack_start(&details, false, false, 0);
+        labels.show();
+        let content_area = info_bar.get_content_area().unwrap().downcast::<gtk::Box>().unwrap();
+        content_area.pack_start(&labels, true, true, 0);
+
+        let error_bar = Rc::new(ErrorBar {
+            info_bar: info_bar.clone(),
+            summary,
+            details,
+            summary_text: RefCell::new(String::new()),
+            last_report: Cell::new(None),
+            suppressed: Cell::new(0),
+        });
+
+        {
+            let error_bar = error_bar.clone();
+            info_bar.connect_response(move |_, response| {
+                if response == details_response {
+                    let visible = error_bar.details.get_visible();
+                    error_bar.details.set_visible(!visible);
+                } else {
+                    error_bar.dismiss();
+                }
+            });
+        }
+
+        error_bar
+    }
+
+    pub fn widget(&self) -> &InfoBar {
+        &self.info_bar
+    }
+
+    pub fn report(&self, summary: &str, details: &str) {
+        let now = Instant::now();
+        let rate_limited = self.info_bar.get_visible() && match self.last_report.get() {
+            Some(last_report) => now.duration_since(last_report) < Duration::from_secs(RATE_LIMIT),
+            None => false,
+        };
+        self.last_report.set(Some(now));
+
+        if rate_limited {
+            let suppressed = self.suppressed.get() + 1;
+            self.suppressed.set(suppressed);
+            let text = format!("{} (and {} more)", self.summary_text.borrow(), suppressed);
+            self.summary.set_text(&text);
+            return;
+        }
+
+        self.suppressed.set(0);
+        *self.summary_text.borrow_mut() = summary.to_owned();
+        self.summary.set_text(summary);
+        self.details.set_text(details);
+        self.details.hide();
+        self.info_bar.show();
+    }
+
+    fn dismiss(&self) {
+        self.info_bar.hide();
+        self.suppressed.set(0);
+    }
+}
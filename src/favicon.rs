@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use gdk_pixbuf::{Colorspace, Pixbuf};
+use glib_itc::{Receiver, Sender, channel};
+use gtk::{Continue, Window, WindowExt};
+use hyper::Client;
+use hyper::net::HttpsConnector;
+use hyper_openssl::OpensslClient;
+use image;
+use servo::servo_url::ServoUrl;
+
+// A decoded icon, as RGBA rows
+struct Icon {
+    url: ServoUrl,
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+}
+
+// Fetches and decodes favicons off the main thread, then makes them the
+// window's icon. Broken icons are ignored. Loaded icons are kept, switching
+// back to a tab shows its icon again without fetching it.
+pub struct FaviconLoader {
+    gtk_window: Window,
+    tx: Arc<Mutex<Sender>>,
+    // Kept alive for the main loop to keep listening
+    _rx: Receiver,
+    loaded: Arc<Mutex<Option<Icon>>>,
+    // Icons that arrive after another one was requested are dropped
+    requested: Rc<RefCell<Option<ServoUrl>>>,
+    icons: Rc<RefCell<HashMap<ServoUrl, Pixbuf>>>,
+}
+
+impl FaviconLoader {
+    pub fn new(gtk_window: &Window) -> FaviconLoader {
+        let (tx, mut rx) = channel();
+        let loaded: Arc<Mutex<Option<Icon>>> = Arc::new(Mutex::new(None));
+        let requested: Rc<RefCell<Option<ServoUrl>>> = Rc::new(RefCell::new(None));
+        let icons: Rc<RefCell<HashMap<ServoUrl, Pixbuf>>> = Rc::new(RefCell::new(HashMap::new()));
+        {
+            let gtk_window = gtk_window.clone();
+            let loaded = loaded.clone();
+            let requested = requested.clone();
+            let icons = icons.clone();
+            rx.connect_recv(move || {
+                let icon = loaded.lock().unwrap().take();
+                if let Some(icon) = icon {
+                    let pixbuf = Pixbuf::new_from_vec(icon.pixels, Colorspace::Rgb, true, 8,
+                                                      icon.width, icon.height, icon.width * 4);
+                    if requested.borrow().as_ref() == Some(&icon.url) {
+                        gtk_window.set_icon(Some(&pixbuf));
+                    }
+                    icons.borrow_mut().insert(icon.url, pixbuf);
+                }
+                Continue(true)
+            });
+        }
+        FaviconLoader {
+            gtk_window: gtk_window.clone(),
+            tx: Arc::new(Mutex::new(tx)),
+            _rx: rx,
+            loaded,
+            requested,
+            icons,
+        }
+    }
+
+    // Back to the application's icon, for pages without one
+    pub fn clear(&self) {
+        *self.requested.borrow_mut() = None;
+        self.gtk_window.set_icon(None);
+    }
+
+    pub fn load(&self, url: ServoUrl) {
+        *self.requested.borrow_mut() = Some(url.clone());
+        if let Some(pixbuf) = self.icons.borrow().get(&url) {
+            self.gtk_window.set_icon(Some(pixbuf));
+            return;
+        }
+        let tx = self.tx.clone();
+        let loaded = self.loaded.clone();
+        let spawned = thread::Builder::new().name("Favicon".to_owned()).spawn(move || {
+            if let Some(icon) = fetch(&url).and_then(|bytes| decode(url, &bytes)) {
+                *loaded.lock().unwrap() = Some(icon);
+                tx.lock().unwrap().send();
+            }
+        });
+        if let Err(error) = spawned {
+            eprintln!("Can't start loading the favicon: {}", error);
+        }
+    }
+}
+
+fn fetch(url: &ServoUrl) -> Option<Vec<u8>> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    let ssl = match OpensslClient::new() {
+        Ok(ssl) => ssl,
+        Err(_) => return None,
+    };
+    let client = Client::with_connector(HttpsConnector::new(ssl));
+    let mut response = match client.get(url.as_str()).send() {
+        Ok(response) => response,
+        Err(_) => return None,
+    };
+    if !response.status.is_success() {
+        return None;
+    }
+    let mut bytes = vec![];
+    match response.read_to_end(&mut bytes) {
+        Ok(_) => Some(bytes),
+        Err(_) => None,
+    }
+}
+
+// PNG and ICO are the usual formats, the image crate guesses which it is
+fn decode(url: ServoUrl, bytes: &[u8]) -> Option<Icon> {
+    let image = match image::load_from_memory(bytes) {
+        Ok(image) => image.to_rgba(),
+        Err(_) => return None,
+    };
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some(Icon {
+        url,
+        width,
+        height,
+        pixels: image.into_raw(),
+    })
+}
@@ -0,0 +1,74 @@
+use base64;
+use servo::{BrowserId, Servo};
+use servo::compositing::windowing::WindowEvent;
+use servo::servo_url::ServoUrl;
+
+use ServoWindow;
+
+// Servo doesn't enforce a limit, but very long data: URLs are slow to parse
+// and other engines cap them at 2MB
+const DATA_URL_WARNING_LENGTH: usize = 2 * 1024 * 1024;
+
+// Encodes raw HTML into a data: URL. If a base URL is given, a <base> element
+// is injected so relative resources resolve against it.
+pub fn html_to_data_url(html: &str, base_url: Option<&ServoUrl>) -> ServoUrl {
+    let html = match base_url {
+        Some(base_url) => inject_base(html, base_url),
+        None => html.to_owned(),
+    };
+    // Base64 keeps non-ASCII content and characters like `#` intact
+    let url = format!("data:text/html;charset=utf-8;base64,{}", base64::encode(html.as_bytes()));
+    if url.len() > DATA_URL_WARNING_LENGTH {
+        eprintln!("Warning: the HTML is {} bytes long once encoded, it might not load", url.len());
+    }
+    ServoUrl::parse(&url).unwrap()
+}
+
+pub fn load_html(servo: &mut Servo<ServoWindow>, browser_id: BrowserId, html: &str, base_url: Option<&ServoUrl>) {
+    let url = html_to_data_url(html, base_url);
+    servo.handle_events(vec![WindowEvent::LoadUrl(browser_id, url)]);
+}
+
+// Shown in place of a page that failed to load. Retrying loads the page
+// again, replacing the error page.
+pub fn error_page(url: &str, error: &str) -> String {
+    format!("<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Problem loading page</title>
+<style>
+body {{ font-family: sans-serif; max-width: 40em; margin: 4em auto; color: #333; }}
+code {{ word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>Problem loading page</h1>
+<p><code>{url}</code> could not be loaded.</p>
+<p>Error: <code>{error}</code></p>
+<p><a href=\"{url}\">Retry</a></p>
+</body>
+</html>
+", url = escape(url), error = escape(error))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+// A <base> element before the document content ends up in <head>. It has to
+// come after the doctype though, or the page would be rendered in quirks mode.
+fn inject_base(html: &str, base_url: &ServoUrl) -> String {
+    let base = format!("<base href=\"{}\">", escape(base_url.as_str()));
+
+    let trimmed = html.trim_left();
+    if trimmed.get(..9).map_or(false, |start| start.to_lowercase() == "<!doctype") {
+        if let Some(end) = trimmed.find('>') {
+            let (doctype, rest) = trimmed.split_at(end + 1);
+            return format!("{}{}{}", doctype, base, rest);
+        }
+    }
+    format!("{}{}", base, html)
+}
@@ -0,0 +1,216 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gdk::{self, EventKey, CONTROL_MASK, MOD1_MASK, SHIFT_MASK, SUPER_MASK};
+use gdk::enums::key;
+use gtk::{GLArea, Inhibit, WidgetExt};
+use servo::Servo;
+use servo::compositing::windowing::WindowEvent;
+use servo::msg::constellation_msg::{self, Key, KeyModifiers, KeyState};
+
+use scroll::SmoothScroller;
+use LINE_HEIGHT;
+use ServoWindow;
+
+// Modifiers held during a key event
+pub fn modifiers(event: &EventKey) -> KeyModifiers {
+    let state = event.get_state();
+    let mut modifiers = KeyModifiers::empty();
+    if state.contains(SHIFT_MASK) {
+        modifiers.insert(constellation_msg::SHIFT);
+    }
+    if state.contains(CONTROL_MASK) {
+        modifiers.insert(constellation_msg::CONTROL);
+    }
+    if state.contains(MOD1_MASK) {
+        modifiers.insert(constellation_msg::ALT);
+    }
+    if state.contains(SUPER_MASK) {
+        modifiers.insert(constellation_msg::SUPER);
+    }
+    modifiers
+}
+
+// The text typed by a key press, if any. Shortcuts (Ctrl or Alt held) and
+// control keys such as Enter or Backspace don't type anything.
+pub fn typed_char(event: &EventKey) -> Option<char> {
+    let state = event.get_state();
+    if state.contains(CONTROL_MASK) || state.contains(MOD1_MASK) {
+        return None;
+    }
+    gdk::keyval_to_unicode(event.get_keyval()).and_then(|ch| if ch.is_control() { None } else { Some(ch) })
+}
+
+// Servo's keys are physical keys of a US layout: shifted symbols map to the
+// key they are typed with
+pub fn servo_key(keyval: u32) -> Option<Key> {
+    let key = match keyval {
+        key::space => Key::Space,
+        key::apostrophe | key::quotedbl => Key::Apostrophe,
+        key::comma | key::less => Key::Comma,
+        key::minus | key::underscore => Key::Minus,
+        key::period | key::greater => Key::Period,
+        key::slash | key::question => Key::Slash,
+        key::_0 | key::parenright => Key::Num0,
+        key::_1 | key::exclam => Key::Num1,
+        key::_2 | key::at => Key::Num2,
+        key::_3 | key::numbersign => Key::Num3,
+        key::_4 | key::dollar => Key::Num4,
+        key::_5 | key::percent => Key::Num5,
+        key::_6 | key::asciicircum => Key::Num6,
+        key::_7 | key::ampersand => Key::Num7,
+        key::_8 | key::asterisk => Key::Num8,
+        key::_9 | key::parenleft => Key::Num9,
+        key::semicolon | key::colon => Key::Semicolon,
+        key::equal | key::plus => Key::Equal,
+        key::a | key::A => Key::A,
+        key::b | key::B => Key::B,
+        key::c | key::C => Key::C,
+        key::d | key::D => Key::D,
+        key::e | key::E => Key::E,
+        key::f | key::F => Key::F,
+        key::g | key::G => Key::G,
+        key::h | key::H => Key::H,
+        key::i | key::I => Key::I,
+        key::j | key::J => Key::J,
+        key::k | key::K => Key::K,
+        key::l | key::L => Key::L,
+        key::m | key::M => Key::M,
+        key::n | key::N => Key::N,
+        key::o | key::O => Key::O,
+        key::p | key::P => Key::P,
+        key::q | key::Q => Key::Q,
+        key::r | key::R => Key::R,
+        key::s | key::S => Key::S,
+        key::t | key::T => Key::T,
+        key::u | key::U => Key::U,
+        key::v | key::V => Key::V,
+        key::w | key::W => Key::W,
+        key::x | key::X => Key::X,
+        key::y | key::Y => Key::Y,
+        key::z | key::Z => Key::Z,
+        key::bracketleft | key::braceleft => Key::LeftBracket,
+        key::backslash | key::bar => Key::Backslash,
+        key::bracketright | key::braceright => Key::RightBracket,
+        key::grave | key::asciitilde => Key::GraveAccent,
+
+        key::Escape => Key::Escape,
+        key::Return => Key::Enter,
+        // Shift+Tab
+        key::Tab | key::ISO_Left_Tab => Key::Tab,
+        key::BackSpace => Key::Backspace,
+        key::Insert => Key::Insert,
+        key::Delete => Key::Delete,
+        key::Right => Key::Right,
+        key::Left => Key::Left,
+        key::Down => Key::Down,
+        key::Up => Key::Up,
+        key::Page_Up => Key::PageUp,
+        key::Page_Down => Key::PageDown,
+        key::Home => Key::Home,
+        key::End => Key::End,
+        key::Caps_Lock => Key::CapsLock,
+        key::Scroll_Lock => Key::ScrollLock,
+        key::Num_Lock => Key::NumLock,
+        key::Print => Key::PrintScreen,
+        key::Pause => Key::Pause,
+        key::Menu => Key::Menu,
+
+        key::F1 => Key::F1,
+        key::F2 => Key::F2,
+        key::F3 => Key::F3,
+        key::F4 => Key::F4,
+        key::F5 => Key::F5,
+        key::F6 => Key::F6,
+        key::F7 => Key::F7,
+        key::F8 => Key::F8,
+        key::F9 => Key::F9,
+        key::F10 => Key::F10,
+        key::F11 => Key::F11,
+        key::F12 => Key::F12,
+
+        key::KP_0 | key::KP_Insert => Key::Kp0,
+        key::KP_1 | key::KP_End => Key::Kp1,
+        key::KP_2 | key::KP_Down => Key::Kp2,
+        key::KP_3 | key::KP_Page_Down => Key::Kp3,
+        key::KP_4 | key::KP_Left => Key::Kp4,
+        key::KP_5 | key::KP_Begin => Key::Kp5,
+        key::KP_6 | key::KP_Right => Key::Kp6,
+        key::KP_7 | key::KP_Home => Key::Kp7,
+        key::KP_8 | key::KP_Up => Key::Kp8,
+        key::KP_9 | key::KP_Page_Up => Key::Kp9,
+        key::KP_Decimal | key::KP_Delete => Key::KpDecimal,
+        key::KP_Divide => Key::KpDivide,
+        key::KP_Multiply => Key::KpMultiply,
+        key::KP_Subtract => Key::KpSubtract,
+        key::KP_Add => Key::KpAdd,
+        key::KP_Enter => Key::KpEnter,
+        key::KP_Equal => Key::KpEqual,
+
+        key::Shift_L => Key::LeftShift,
+        key::Shift_R => Key::RightShift,
+        key::Control_L => Key::LeftControl,
+        key::Control_R => Key::RightControl,
+        key::Alt_L => Key::LeftAlt,
+        key::Alt_R => Key::RightAlt,
+        key::Super_L => Key::LeftSuper,
+        key::Super_R => Key::RightSuper,
+
+        key::Back => Key::NavigateBackward,
+        key::Forward => Key::NavigateForward,
+        _ => return None,
+    };
+    Some(key)
+}
+
+// Sends the keys typed in the GLArea to servo, and scrolls with the
+// pointer's position once the page didn't use them
+pub fn connect(gl_area: &GLArea, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>,
+               pointer: Rc<RefCell<(f64, f64)>>, scroller: SmoothScroller) {
+    // Keys go to the page first, the ones it doesn't use scroll it
+    let unhandled_key: Box<Fn(Key, KeyModifiers)> = {
+        let gl_area = gl_area.clone();
+        let window = window.clone();
+        Box::new(move |key, modifiers| {
+            if !modifiers.is_empty() {
+                return;
+            }
+            let page_height = gl_area.get_allocated_height() as f32 - LINE_HEIGHT;
+            let pointer = window.device_point(*pointer.borrow());
+            let pointer = (pointer.x as i32, pointer.y as i32);
+            match key {
+                Key::Up => scroller.scroll_by(0.0, LINE_HEIGHT, pointer),
+                Key::Down => scroller.scroll_by(0.0, -LINE_HEIGHT, pointer),
+                Key::Left => scroller.scroll_by(LINE_HEIGHT, 0.0, pointer),
+                Key::Right => scroller.scroll_by(-LINE_HEIGHT, 0.0, pointer),
+                Key::PageUp => scroller.scroll_by(0.0, page_height, pointer),
+                Key::PageDown => scroller.scroll_by(0.0, -page_height, pointer),
+                _ => {},
+            }
+        })
+    };
+    *window.unhandled_key.borrow_mut() = Some(unhandled_key);
+
+    {
+        let servo = servo.clone();
+        gl_area.connect_key_press_event(move |_, event| {
+            if let Some(key) = servo_key(event.get_keyval()) {
+                let event = WindowEvent::KeyEvent(typed_char(event), key, KeyState::Pressed, modifiers(event));
+                servo.borrow_mut().handle_events(vec![event]);
+            }
+            // Tab and arrows move the focus in the page, not between widgets
+            Inhibit(true)
+        });
+    }
+
+    {
+        let servo = servo.clone();
+        gl_area.connect_key_release_event(move |_, event| {
+            if let Some(key) = servo_key(event.get_keyval()) {
+                let event = WindowEvent::KeyEvent(None, key, KeyState::Released, modifiers(event));
+                servo.borrow_mut().handle_events(vec![event]);
+            }
+            Inhibit(true)
+        });
+    }
+}
@@ -1,40 +1,78 @@
+extern crate base64;
 extern crate epoxy;
 extern crate gdk;
+extern crate gdk_pixbuf;
+extern crate gio;
+extern crate glib;
 extern crate glib_itc;
 extern crate gtk;
+extern crate hyper;
+extern crate hyper_openssl;
+extern crate image;
+extern crate libc;
+extern crate pango;
 extern crate servo;
 extern crate shared_library;
+extern crate url;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::env;
+use std::mem;
+use std::process;
 use std::ptr;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
 
-use gdk::{Display, ScrollDirection, POINTER_MOTION_MASK, SCROLL_MASK};
-use gdk::enums::key;
-use glib_itc::{Sender, channel};
+use gdk::{
+    Display,
+    BUTTON_PRESS_MASK,
+    BUTTON_RELEASE_MASK,
+    KEY_PRESS_MASK,
+    KEY_RELEASE_MASK,
+    POINTER_MOTION_MASK,
+    SCROLL_MASK,
+    SMOOTH_SCROLL_MASK,
+};
+use glib::StaticType;
+use glib_itc::{Receiver, Sender, channel};
+use gio::{ApplicationExt, ApplicationExtManual, FileExt};
 use gtk::{
+    Align,
+    ButtonsType,
+    Cast,
     ContainerExt,
     Continue,
+    DialogExt,
     GLArea,
     GLAreaExt,
+    GestureSingleExt,
+    GestureSwipe,
     Inhibit,
+    Label,
+    MessageDialog,
+    MessageType,
+    OverlayExt,
+    ResponseType,
     WidgetExt,
     Window,
     WindowExt,
     WindowType,
+    DIALOG_DESTROY_WITH_PARENT,
+    DIALOG_MODAL,
 };
 use gtk::Orientation::Vertical;
-use servo::BrowserId;
+use servo::{BrowserId, Servo};
 use servo::compositing::compositor_thread::EventLoopWaker;
-use servo::compositing::windowing::{WindowEvent, WindowMethods};
-use servo::euclid::{Point2D, ScaleFactor, Size2D, TypedPoint2D, TypedRect, TypedSize2D, TypedVector2D};
+use servo::compositing::windowing::{WebRenderDebugOption, WindowEvent, WindowMethods};
+use servo::euclid::{Point2D, ScaleFactor, Size2D, TypedPoint2D, TypedRect, TypedSize2D};
 use servo::gl;
 use servo::ipc_channel::ipc;
-use servo::msg::constellation_msg::{Key, KeyModifiers};
+use servo::msg::constellation_msg::{Key, KeyModifiers, TraversalDirection};
 use servo::net_traits::net_error_list::NetError;
-use servo::script_traits::{LoadData, TouchEventType};
+use servo::script_traits::LoadData;
 use servo::servo_config::opts;
 use servo::servo_config::resource_files::set_resources_path;
 use servo::servo_geometry::DeviceIndependentPixel;
@@ -43,145 +81,1031 @@ use servo::style_traits::cursor::Cursor;
 use servo::style_traits::DevicePixel;
 use shared_library::dynamic_library::DynamicLibrary;
 
+mod benchmark;
+mod edit;
+mod error_bar;
+mod favicon;
+mod html;
+mod keys;
+mod memory;
+mod motion;
+mod options;
+mod pointer;
+mod render_fallback;
+mod screenshot;
+mod scroll;
+mod shortcuts;
+mod splash;
+mod status;
+mod tabs;
+mod theme;
+mod undo_bar;
+mod url_bar;
+
+use benchmark::Benchmark;
+use error_bar::ErrorBar;
+use favicon::FaviconLoader;
+use options::{LastTabClose, Options, TextAntialiasing, url_from_arg};
+use render_fallback::RenderFallback;
+use screenshot::AreaSelection;
+use scroll::SmoothScroller;
+use splash::Splash;
+use status::StatusBar;
+use tabs::Tabs;
+use theme::Theme;
+use undo_bar::UndoBar;
+use url_bar::UrlBar;
+
+// Roughly one frame at 60Hz
+const FRAME_INTERVAL_MS: u32 = 16;
+
+// Overrides the device pixels per CSS pixel, to try HiDPI rendering on any
+// screen. The frame keeps the buffer's size, pages get a smaller viewport.
+const DEVICE_PIXEL_RATIO_VAR: &str = "SERVO_DEVICE_PIXEL_RATIO";
+
+// Time given to a loaded page to paint before --screenshot saves it, and
+// how long --screenshot waits for the page to load at all
+const SCREENSHOT_SETTLE_MS: u32 = 500;
+const SCREENSHOT_TIMEOUT_S: u32 = 60;
+
+// Quiet time after the last resize before servo is told about the new size
+const RESIZE_DEBOUNCE_MS: u32 = 50;
+
+// Distance scrolled by one arrow key press, and by one wheel notch
+const LINE_HEIGHT: f32 = 38.0;
+
+// Each zoom step scales the page by this factor, or its inverse
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.3;
+const MAX_ZOOM: f32 = 3.0;
+
+const DEFAULT_URL: &str = "https://servo.org";
+
+// How long servo gets to shut down before the window is closed anyway
+const QUIT_TIMEOUT_S: u32 = 5;
+
+// Used by GApplication to find an already running instance
+const APPLICATION_ID: &str = "org.servo.EmbeddingExample";
+
+// Widgets servo draws into or reports to
+#[derive(Clone)]
+struct Ui {
+    gtk_window: Window,
+    gl_area: GLArea,
+    tabs: Option<Rc<Tabs>>,
+    url_bar: Option<Rc<UrlBar>>,
+    error_bar: Option<Rc<ErrorBar>>,
+    undo_bar: Option<Rc<UndoBar>>,
+    render_fallback: Rc<RenderFallback>,
+    memory_label: Label,
+    splash: Option<Rc<Splash>>,
+    status_bar: Option<Rc<StatusBar>>,
+    theme: Option<Rc<Theme>>,
+    // Widgets don't own their gestures, they have to be kept alive
+    swipe: GestureSwipe,
+}
+
+// Opening and closing tabs, shared by the tab bar and the shortcuts
+struct TabActions {
+    open: Rc<Fn()>,
+    close: Rc<Fn(BrowserId)>,
+}
+
+// What undoing a tab close reopens. Servo doesn't tell embedders where
+// pages are scrolled, so the page opens at the top.
+struct ClosedTab {
+    url: ServoUrl,
+    position: u32,
+    zoom: f32,
+}
+
+// Set once servo is running, does something with a URL
+type UrlHandler = Rc<RefCell<Option<Box<Fn(ServoUrl)>>>>;
+
+// Of the whole process, set when a headless run fails
+type ExitCode = Rc<Cell<i32>>;
+
 fn main() {
     gtk::init().unwrap();
 
+    let options = Rc::new(Options::from_args());
+
     println!("Servo version: {}", servo::config::servo_version());
 
+    // A second launch hands its URLs over to the running instance and exits
+    let flags = if options.new_instance {
+        gio::APPLICATION_HANDLES_OPEN | gio::APPLICATION_NON_UNIQUE
+    } else {
+        gio::APPLICATION_HANDLES_OPEN
+    };
+    let app = gtk::Application::new(Some(APPLICATION_ID), flags).unwrap();
+    let url_handler: UrlHandler = Rc::new(RefCell::new(None));
+    let exit_code: ExitCode = Rc::new(Cell::new(0));
+    let ui: Rc<RefCell<Option<Ui>>> = Rc::new(RefCell::new(None));
+
+    // Launched without URLs
+    {
+        let options = options.clone();
+        let url_handler = url_handler.clone();
+        let exit_code = exit_code.clone();
+        let ui = ui.clone();
+        app.connect_activate(move |app| {
+            let mut ui = ui.borrow_mut();
+            if ui.is_none() {
+                *ui = Some(build_ui(app, &options, vec![], url_handler.clone(), exit_code.clone()));
+            } else {
+                ui.as_ref().unwrap().gtk_window.present();
+            }
+        });
+    }
+
+    // Launched with URLs, either by us or by another instance
+    {
+        let options = options.clone();
+        let url_handler = url_handler.clone();
+        let exit_code = exit_code.clone();
+        let ui = ui.clone();
+        app.connect_open(move |app, files, _| {
+            let urls: Vec<ServoUrl> = files.iter()
+                .filter_map(|file| file.get_uri())
+                .filter_map(|uri| ServoUrl::parse(&uri).ok())
+                .collect();
+            let mut ui = ui.borrow_mut();
+            if ui.is_none() {
+                *ui = Some(build_ui(app, &options, urls, url_handler.clone(), exit_code.clone()));
+                return;
+            }
+            ui.as_ref().unwrap().gtk_window.present();
+            match *url_handler.borrow() {
+                Some(ref load_url) => for url in urls {
+                    load_url(url);
+                },
+                None => eprintln!("Servo isn't running yet, ignoring {} URL(s)", urls.len()),
+            }
+        });
+    }
+
+    // Our own flags were already parsed, GApplication only gets the URLs.
+    // Without a valid one, the homepage is loaded.
+    let mut args: Vec<String> = env::args().take(1).collect();
+    args.extend(options.urls.iter()
+                    .filter_map(|arg| url_from_arg(arg))
+                    .map(|url| url.as_str().to_owned()));
+    app.run(&args);
+    if exit_code.get() != 0 {
+        process::exit(exit_code.get());
+    }
+}
+
+fn build_ui(app: &gtk::Application, options: &Rc<Options>, urls: Vec<ServoUrl>, url_handler: UrlHandler,
+            exit_code: ExitCode) -> Ui {
     let gtk_window = Window::new(WindowType::Toplevel);
+    gtk_window.set_application(Some(app));
     gtk_window.set_size_request(800, 600);
-    gtk_window.add_events((POINTER_MOTION_MASK | SCROLL_MASK).bits() as i32);
+    // Touchpads only report precise, diagonal deltas with smooth scrolling
+    gtk_window.add_events((POINTER_MOTION_MASK | SCROLL_MASK | SMOOTH_SCROLL_MASK).bits() as i32);
 
     let vbox = gtk::Box::new(Vertical, 0);
     gtk_window.add(&vbox);
 
+    if options.kiosk {
+        gtk_window.fullscreen();
+    }
+
+    let tabs = if options.kiosk {
+        None
+    } else {
+        let tabs = Rc::new(Tabs::new(options.tab_min_width, options.tab_max_width, options.close_buttons));
+        vbox.add(tabs.widget());
+        Some(tabs)
+    };
+
+    let url_bar = if options.kiosk {
+        None
+    } else {
+        let url_bar = Rc::new(UrlBar::new());
+        vbox.add(url_bar.widget());
+        Some(url_bar)
+    };
+
+    let error_bar = if options.error_bar && !options.kiosk {
+        let error_bar = ErrorBar::new();
+        vbox.add(error_bar.widget());
+        Some(error_bar)
+    } else {
+        None
+    };
+
+    let undo_bar = if options.tab_close_undo_secs > 0 && !options.kiosk {
+        let undo_bar = Rc::new(UndoBar::new(options.tab_close_undo_secs));
+        vbox.add(undo_bar.widget());
+        Some(undo_bar)
+    } else {
+        None
+    };
+
     let gl_area = GLArea::new();
     gl_area.set_auto_render(false);
     gl_area.set_has_depth_buffer(true);
-    gl_area.add_events((POINTER_MOTION_MASK | SCROLL_MASK).bits() as i32);
+    gl_area.add_events((POINTER_MOTION_MASK | SCROLL_MASK | SMOOTH_SCROLL_MASK | BUTTON_PRESS_MASK |
+                        BUTTON_RELEASE_MASK | KEY_PRESS_MASK | KEY_RELEASE_MASK).bits() as i32);
+    // Keys typed in the page are sent to the focused GLArea
+    gl_area.set_can_focus(true);
     gl_area.set_vexpand(true);
-    vbox.add(&gl_area);
+    // Letterboxed: the window can grow but the page keeps its size
+    if let Some((width, height)) = options.logical_size {
+        gl_area.set_size_request(width as i32, height as i32);
+        gl_area.set_halign(Align::Center);
+        gl_area.set_valign(Align::Center);
+    }
+    let render_fallback = Rc::new(RenderFallback::new(&gl_area));
+    vbox.add(render_fallback.widget());
 
-    gtk_window.connect_delete_event(|_, _| {
-        gtk::main_quit();
-        Inhibit(false)
-    });
+    let splash = if options.splash {
+        let splash = Rc::new(Splash::new());
+        render_fallback.widget().add_overlay(splash.widget());
+        Some(splash)
+    } else {
+        None
+    };
 
-    gtk_window.show_all();
+    let status_bar = if options.kiosk {
+        None
+    } else {
+        let status_bar = Rc::new(StatusBar::new());
+        render_fallback.widget().add_overlay(status_bar.widget());
+        Some(status_bar)
+    };
 
-    gl_area.make_current();
+    let memory_label = memory::memory_label(options.show_memory && !options.kiosk);
+    vbox.add(&memory_label);
 
-    epoxy::load_with(|s| {
-        unsafe {
-            match DynamicLibrary::open(None).unwrap().symbol(s) {
-                Ok(v) => v,
-                Err(_) => ptr::null(),
+    // Two fingers on a touchscreen: one-finger drags select text, and mouse
+    // drags and touchpad scrolls are left to the page too. The number of
+    // points can only be given when the gesture is created.
+    let properties: &[(&str, &glib::ToValue)] = &[("widget", &gl_area), ("n-points", &2u32)];
+    let swipe: GestureSwipe = glib::Object::new(GestureSwipe::static_type(), properties)
+        .unwrap()
+        .downcast()
+        .unwrap();
+    swipe.set_touch_only(true);
+
+    let theme = options.theme.as_ref().map(|theme| Rc::new(Theme::new(theme)));
+
+    let ui = Ui {
+        gtk_window: gtk_window.clone(),
+        gl_area: gl_area.clone(),
+        tabs,
+        url_bar,
+        error_bar,
+        undo_bar,
+        render_fallback,
+        memory_label,
+        splash,
+        status_bar,
+        theme,
+        swipe,
+    };
+
+    // The GL context only exists once the GLArea is realized (while the window
+    // is shown). Setting up GL or servo before that gives a broken context.
+    {
+        let ui = ui.clone();
+        let options = options.clone();
+        let urls = RefCell::new(urls);
+        let started = Cell::new(false);
+        gl_area.connect_realize(move |gl_area| {
+            if started.get() {
+                return;
             }
-        }
+            gl_area.make_current();
+            if let Some(error) = gl_area.get_error() {
+                eprintln!("Can't create the GL context: {}", error);
+                return;
+            }
+            started.set(true);
+            let urls = mem::replace(&mut *urls.borrow_mut(), vec![]);
+            start_servo(&ui, &options, urls, url_handler.clone(), exit_code.clone());
+        });
+    }
+
+    // Clicking the page takes the keyboard away from the address bar
+    gl_area.connect_button_press_event(|gl_area, _| {
+        gl_area.grab_focus();
+        Inhibit(false)
     });
-    let gl = unsafe {
-        gl::GlFns::load_with(epoxy::get_proc_addr)
-    };
+
+    if options.screenshot.is_some() {
+        // The window is never mapped, so GTK neither sizes the GLArea nor
+        // realizes it (which creates the GL context and starts servo)
+        let (width, height) = options.logical_size.unwrap_or((800, 600));
+        gl_area.size_allocate(&mut gtk::Allocation { x: 0, y: 0, width: width as i32, height: height as i32 });
+        gl_area.realize();
+    } else {
+        gtk_window.show_all();
+        // GTK focuses the first entry when the window is shown
+        gl_area.grab_focus();
+    }
+
+    ui
+}
+
+// The first URL is loaded in the first tab, the others in tabs of their own
+fn start_servo(ui: &Ui, options: &Options, urls: Vec<ServoUrl>, url_handler: UrlHandler, exit_code: ExitCode) {
+    let Ui {
+        ref gtk_window,
+        ref gl_area,
+        ref tabs,
+        ref url_bar,
+        ref error_bar,
+        ref render_fallback,
+        ref splash,
+        ref status_bar,
+        ..
+    } = *ui;
+
+    let gl = load_gl();
 
     let path = env::current_dir().unwrap().join("resources");
     let path = path.to_str().unwrap().to_string();
     set_resources_path(Some(path));
 
-    let opts = opts::default_opts();
+    let mut opts = opts::default_opts();
+    opts.enable_text_antialiasing = options.text_antialiasing != TextAntialiasing::None;
+    opts.enable_subpixel_text_antialiasing = options.text_antialiasing == TextAntialiasing::Subpixel;
     opts::set_defaults(opts);
 
-    let (tx, mut rx) = channel();
+    let (tx, rx) = channel();
 
     let waker = Box::new(GtkEventLoopWaker {
         tx: Arc::new(Mutex::new(tx)),
     });
 
+    let navigation_allowlist = if options.kiosk {
+        Some(kiosk_allowlist(options))
+    } else {
+        None
+    };
+
+    // Benchmark loads replace the selected page, once servo is running
+    let load_in_page: UrlHandler = Rc::new(RefCell::new(None));
+    let benchmark = options.benchmark.as_ref().map(|urls| new_benchmark(options, urls, gtk_window, &load_in_page));
+
     let window = Rc::new(ServoWindow {
         gl_area: gl_area.clone(),
         gtk_window: gtk_window.clone(),
+        url_bar: url_bar.clone(),
         waker,
         gl,
+        error_bar: error_bar.clone(),
+        render_fallback: render_fallback.clone(),
+        splash: splash.clone(),
+        status_bar: status_bar.clone(),
+        selected_browser: Cell::new(None),
+        pages: RefCell::new(HashMap::new()),
+        navigation_allowlist,
+        confirmed_schemes: options.confirm_navigation.clone(),
+        logical_size: options.logical_size,
+        device_pixel_ratio: device_pixel_ratio(),
+        kiosk: options.kiosk,
+        benchmark: benchmark.clone(),
+        event_sender: RefCell::new(None),
+        unhandled_key: RefCell::new(None),
+        pending_browsers: RefCell::new(vec![]),
+        loaded: RefCell::new(None),
+        title_observer: RefCell::new(None),
+        zoom_observer: RefCell::new(None),
+        favicons: FaviconLoader::new(gtk_window),
+        cursors: RefCell::new(HashMap::new()),
+        area_selection: Rc::new(AreaSelection::new()),
     });
 
+    // Tabs are labelled by their page's title, and zoom unless it's 100%
+    if let Some(ref tabs) = *tabs {
+        let title_observer: Box<Fn(BrowserId, Option<String>)> = {
+            let tabs = tabs.clone();
+            let window = window.clone();
+            Box::new(move |browser_id, _| tabs.set_title(browser_id, &window.tab_title(browser_id)))
+        };
+        *window.title_observer.borrow_mut() = Some(title_observer);
+        let zoom_observer: Box<Fn(BrowserId, f32)> = {
+            let tabs = tabs.clone();
+            Box::new(move |browser_id, zoom| tabs.set_zoom(browser_id, zoom))
+        };
+        *window.zoom_observer.borrow_mut() = Some(zoom_observer);
+    }
+
     let servo = Rc::new(RefCell::new(servo::Servo::new(window.clone())));
 
+    // The GL context can't be recreated without restarting servo, but a
+    // transient failure (driver reset, widget unmapped) can be recovered from
+    // by making it current again and compositing a new frame
     {
         let servo = servo.clone();
-        rx.connect_recv(move || {
-            servo.borrow_mut().handle_events(vec![]);
+        let gl_area = gl_area.clone();
+        render_fallback.connect_retry(move || {
+            gl_area.make_current();
+            servo.borrow_mut().handle_events(vec![WindowEvent::Refresh]);
+        });
+    }
+
+    connect_event_loop(gtk_window, &servo, &window, rx);
+
+    // The HTML is loaded once the browser exists. Benchmarks and HTML only
+    // use one page
+    let mut urls = urls.into_iter();
+    let url = match (&benchmark, &options.html) {
+        (&Some(ref benchmark), _) => benchmark.first_url(),
+        (&None, &Some(_)) => ServoUrl::parse("about:blank").unwrap(),
+        (&None, &None) => urls.next().unwrap_or_else(|| homepage(options)),
+    };
+    let other_urls: Vec<ServoUrl> = match (&benchmark, &options.html) {
+        (&None, &None) => urls.collect(),
+        _ => vec![],
+    };
+    if let Some(ref benchmark) = benchmark {
+        benchmark.start();
+        let benchmark = benchmark.clone();
+        gtk::timeout_add_seconds(1, move || {
+            benchmark.check_timeout();
             Continue(true)
         });
     }
 
-    let url = ServoUrl::parse("https://servo.org").unwrap();
-    let (sender, receiver) = ipc::channel().unwrap();
-    servo.borrow_mut().handle_events(vec![WindowEvent::NewBrowser(url, sender)]);
-    let browser_id = receiver.recv().unwrap();
-    servo.borrow_mut().handle_events(vec![WindowEvent::SelectBrowser(browser_id)]);
+    let tab_actions = connect_tabs(ui, options, &servo, &window);
+    open_first_browser(&servo, &window, tabs, options, url, &benchmark, exit_code);
 
-    let pointer = Rc::new(RefCell::new((0.0, 0.0)));
     {
-        let pointer = pointer.clone();
         let servo = servo.clone();
-        gl_area.connect_motion_notify_event(move |_, event| {
-            let (x, y) = event.get_position();
-            *pointer.borrow_mut() = (x, y);
-            let event = WindowEvent::MouseWindowMoveEventClass(TypedPoint2D::new(x as f32, y as f32));
-            servo.borrow_mut().handle_events(vec![event]);
-            Inhibit(false)
-        });
+        let window = window.clone();
+        *load_in_page.borrow_mut() = Some(Box::new(move |url| {
+            match window.selected_browser() {
+                Some(browser_id) => servo.borrow_mut().handle_events(vec![WindowEvent::LoadUrl(browser_id, url)]),
+                None => eprintln!("No browser to load {} in yet", url),
+            }
+        }));
     }
 
+    let open_url = url_opener(&servo, &window, tabs, &load_in_page);
     {
+        let open_url = open_url.clone();
+        *url_handler.borrow_mut() = Some(Box::new(move |url| open_url(url)));
+    }
+
+    // Opened behind the first URL's tab, which stays selected
+    if let Some(ref tabs) = *tabs {
+        for url in other_urls {
+            let tabs = tabs.clone();
+            new_browser(&servo, &window, url, move |browser_id| tabs.append(browser_id));
+        }
+    } else if !other_urls.is_empty() {
+        eprintln!("Without tabs, only the first URL is opened, ignoring {} URL(s)", other_urls.len());
+    }
+
+    if let Some(ref url_bar) = *url_bar {
         let servo = servo.clone();
         let window = window.clone();
-        gl_area.connect_resize(move |_, _, _| {
-            let event = WindowEvent::Resize(window.framebuffer_size());
-            servo.borrow_mut().handle_events(vec![event]);
+        let gl_area = gl_area.clone();
+        url_bar.connect_load(move |url| {
+            gl_area.grab_focus();
+            if let Some(browser_id) = window.selected_browser() {
+                servo.borrow_mut().handle_events(vec![WindowEvent::LoadUrl(browser_id, url)]);
+            }
+        });
+    }
+
+    connect_resize(gl_area, &servo, &window);
+
+    let pointer = pointer::connect(gl_area, gtk_window, &ui.swipe, &servo, &window);
+
+    if options.watch {
+        edit::reload_on_change(servo.clone(), window.clone());
+    }
+
+    let scroller = SmoothScroller::new(servo.clone(), options.scroll_duration_ms, options.scroll_easing);
+    keys::connect(gl_area, &servo, &window, pointer, scroller);
+
+    shortcuts::connect(ui, options, &servo, &window, tab_actions);
+}
+
+fn load_gl() -> Rc<gl::Gl> {
+    epoxy::load_with(|s| {
+        unsafe {
+            match DynamicLibrary::open(None).unwrap().symbol(s) {
+                Ok(v) => v,
+                Err(_) => ptr::null(),
+            }
+        }
+    });
+    unsafe {
+        gl::GlFns::load_with(epoxy::get_proc_addr)
+    }
+}
+
+// Loads and the end of the benchmark are triggered from servo's callbacks,
+// so they wait for the next iteration
+fn new_benchmark(options: &Options, urls: &[ServoUrl], gtk_window: &Window, load_in_page: &UrlHandler)
+                 -> Rc<Benchmark> {
+    let load_in_page = load_in_page.clone();
+    let load = Box::new(move |url: ServoUrl| {
+        let load_in_page = load_in_page.clone();
+        gtk::timeout_add(0, move || {
+            if let Some(ref load_url) = *load_in_page.borrow() {
+                load_url(url.clone());
+            }
+            Continue(false)
+        });
+    });
+    let gtk_window = gtk_window.clone();
+    let finish = Box::new(move || {
+        let gtk_window = gtk_window.clone();
+        gtk::timeout_add(0, move || {
+            gtk_window.close();
+            Continue(false)
+        });
+    });
+    Rc::new(Benchmark::new(urls.to_vec(), options.benchmark_warmup, options.benchmark_repeat,
+                           options.benchmark_output.clone(), load, finish))
+}
+
+// Runs servo whenever it wakes the main loop up, until it has quit
+fn connect_event_loop(gtk_window: &Window, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>,
+                      mut rx: Receiver) {
+    // Closing the window asks servo to quit, once. The window is hidden right
+    // away but only destroyed (ending the application) when servo is done.
+    let quitting = Rc::new(Cell::new(false));
+    let closed = Rc::new(Cell::new(false));
+    {
+        let servo = servo.clone();
+        let quitting = quitting.clone();
+        let closed = closed.clone();
+        gtk_window.connect_delete_event(move |gtk_window, _| {
+            if !quitting.get() {
+                quitting.set(true);
+                gtk_window.hide();
+                servo.borrow_mut().handle_events(vec![WindowEvent::Quit]);
+
+                let gtk_window = gtk_window.clone();
+                let closed = closed.clone();
+                gtk::timeout_add_seconds(QUIT_TIMEOUT_S, move || {
+                    if !closed.get() {
+                        eprintln!("Servo didn't shut down in time, closing anyway");
+                    }
+                    destroy_once(&gtk_window, &closed);
+                    Continue(false)
+                });
+            }
+            Inhibit(true)
         });
     }
 
     {
-        let pointer = pointer.clone();
         let servo = servo.clone();
-        gtk_window.connect_scroll_event(move |_, event| {
-            let (dx, dy) = event.get_delta();
-            let dy = dy * -38.0;
-            let scroll_location = servo::webrender_api::ScrollLocation::Delta(TypedVector2D::new(dx as f32, dy as f32));
-            let phase = match event.get_direction() {
-                ScrollDirection::Down => TouchEventType::Down,
-                ScrollDirection::Up => TouchEventType::Up,
-                ScrollDirection::Left => TouchEventType::Cancel, // FIXME
-                ScrollDirection::Right => TouchEventType::Cancel, // FIXME
-                ScrollDirection::Smooth | _ =>
-                    if dy > 0.0 {
-                        TouchEventType::Down
-                    } else {
-                        TouchEventType::Up
+        *window.event_sender.borrow_mut() = Some(Box::new(move |event| {
+            let servo = servo.clone();
+            let mut event = Some(event);
+            gtk::timeout_add(0, move || {
+                if let Some(event) = event.take() {
+                    servo.borrow_mut().handle_events(vec![event]);
+                }
+                Continue(false)
+            });
+        }));
+    }
+
+    {
+        let servo = servo.clone();
+        let window = window.clone();
+        let gtk_window = gtk_window.clone();
+        rx.connect_recv(move || {
+            // Returns false once servo has finished shutting down
+            let running = servo.borrow_mut().handle_events(vec![]);
+            if !running && quitting.get() {
+                destroy_once(&gtk_window, &closed);
+                return Continue(false);
+            }
+            for (browser_id, created) in window.take_created_browsers() {
+                created(browser_id);
+            }
+            Continue(true)
+        });
+    }
+}
+
+// Servo sits behind the tabs, which open and close its browsers. Returns
+// how to do both, for the shortcuts.
+fn connect_tabs(ui: &Ui, options: &Options, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>)
+                -> TabActions {
+    let Ui {
+        ref gtk_window,
+        ref tabs,
+        ref url_bar,
+        ref undo_bar,
+        ..
+    } = *ui;
+
+    // Only the last closed tab can be reopened, the undo bar doesn't offer
+    // the others
+    let closed_tab: Rc<RefCell<Option<ClosedTab>>> = Rc::new(RefCell::new(None));
+
+    // Selecting a tab selects its browser
+    if let Some(ref tabs) = *tabs {
+        let servo = servo.clone();
+        let window = window.clone();
+        tabs.connect_switch(move |browser_id| {
+            select_browser(&servo, &window, browser_id);
+        });
+    }
+
+    // New tabs start blank, with the address bar focused
+    let open_tab: Rc<Fn()> = {
+        let servo = servo.clone();
+        let window = window.clone();
+        let tabs = tabs.clone();
+        let url_bar = url_bar.clone();
+        Rc::new(move || {
+            if let (&Some(ref tabs), &Some(ref url_bar)) = (&tabs, &url_bar) {
+                let tabs = tabs.clone();
+                let url_bar = url_bar.clone();
+                new_browser(&servo, &window, ServoUrl::parse("about:blank").unwrap(), move |browser_id| {
+                    tabs.add(browser_id);
+                    url_bar.focus();
+                });
+            }
+        })
+    };
+
+    // What closing the last tab does is up to the user
+    let close_tab: Rc<Fn(BrowserId)> = {
+        let servo = servo.clone();
+        let window = window.clone();
+        let tabs = tabs.clone();
+        let gtk_window = gtk_window.clone();
+        let undo_bar = undo_bar.clone();
+        let closed_tab = closed_tab.clone();
+        let last_tab_close = options.last_tab_close;
+        let homepage = homepage(options);
+        Rc::new(move |browser_id| {
+            if tabs.as_ref().map_or(false, |tabs| tabs.is_pinned(browser_id)) {
+                eprintln!("Pinned tabs can't be closed, unpin it first (Ctrl+Shift+P)");
+                return;
+            }
+            let count = tabs.as_ref().map_or(1, |tabs| tabs.count());
+            if count <= 1 {
+                match last_tab_close {
+                    LastTabClose::CloseWindow => gtk_window.close(),
+                    LastTabClose::Homepage => {
+                        let event = WindowEvent::LoadUrl(browser_id, homepage.clone());
+                        servo.borrow_mut().handle_events(vec![event]);
                     },
+                }
+                return;
+            }
+            if let Some(ref tabs) = tabs {
+                // Removing the selected tab selects another one first
+                if window.selected_browser.get() == Some(browser_id) {
+                    if let Some(neighbour) = tabs.neighbour(browser_id) {
+                        tabs.select(neighbour);
+                    }
+                }
+                if let Some(ref undo_bar) = undo_bar {
+                    let url = window.pages.borrow().get(&browser_id).and_then(|page| page.current_url());
+                    if let (Some(url), Some(position)) = (url, tabs.position(browser_id)) {
+                        let zoom = window.zoom(browser_id);
+                        *closed_tab.borrow_mut() = Some(ClosedTab { url, position, zoom });
+                        let title = window.tab_title(browser_id);
+                        if title.is_empty() {
+                            undo_bar.offer("Tab closed");
+                        } else {
+                            undo_bar.offer(&format!("Tab closed: {}", title));
+                        }
+                    }
+                }
+                tabs.remove(browser_id);
+            }
+            window.pages.borrow_mut().remove(&browser_id);
+            servo.borrow_mut().handle_events(vec![WindowEvent::CloseBrowser(browser_id)]);
+        })
+    };
+
+    if let Some(ref tabs) = *tabs {
+        {
+            let open_tab = open_tab.clone();
+            tabs.connect_new_tab(move || open_tab());
+        }
+        {
+            let close_tab = close_tab.clone();
+            tabs.connect_close(move |browser_id| close_tab(browser_id));
+        }
+        let servo = servo.clone();
+        let window = window.clone();
+        tabs.connect_reset_zoom(move |browser_id| reset_zoom(&servo, &window, browser_id));
+    }
+
+    // The tab comes back where it was, zoomed as it was
+    if let (&Some(ref tabs), &Some(ref undo_bar)) = (tabs, undo_bar) {
+        let servo = servo.clone();
+        let window = window.clone();
+        let tabs = tabs.clone();
+        undo_bar.connect_undo(move || {
+            let ClosedTab { url, position, zoom } = match closed_tab.borrow_mut().take() {
+                Some(closed) => closed,
+                None => return,
             };
-            let pointer = {
-                let pointer = pointer.borrow();
-                TypedPoint2D::new(pointer.0 as i32, pointer.1 as i32)
+            let created = {
+                let window = window.clone();
+                let tabs = tabs.clone();
+                move |browser_id| {
+                    tabs.append(browser_id);
+                    tabs.move_to(browser_id, position);
+                    // Selecting the tab applies the zoom
+                    window.set_zoom(browser_id, zoom);
+                    tabs.select(browser_id);
+                }
             };
-            let event = WindowEvent::Scroll(scroll_location, pointer, phase);
-            servo.borrow_mut().handle_events(vec![event]);
-            Inhibit(false)
+            new_browser(&servo, &window, url, created);
         });
     }
 
-    {
+    TabActions {
+        open: open_tab,
+        close: close_tab,
+    }
+}
+
+// The first browser shows the first URL, or --html content
+fn open_first_browser(servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>, tabs: &Option<Rc<Tabs>>,
+                      options: &Options, url: ServoUrl, benchmark: &Option<Rc<Benchmark>>, exit_code: ExitCode) {
+    // --screenshot captures the first browser's page
+    let screenshot_browser = Rc::new(Cell::new(None));
+    if let Some(ref path) = options.screenshot {
+        capture_when_loaded(window, path, screenshot_browser.clone(), exit_code);
+    }
+
+    // The window stays responsive while the first browser is created
+    let created = {
         let servo = servo.clone();
-        gtk_window.connect_key_press_event(move |_, event| {
-            if event.get_keyval() == key::R {
-                let event = WindowEvent::Reload(browser_id);
+        let window = window.clone();
+        let tabs = tabs.clone();
+        let html = match (&options.html, benchmark) {
+            (&Some(ref content), &None) => Some(content.clone()),
+            _ => None,
+        };
+        let base_url = options.base_url.clone();
+        let wr_profiler = options.wr_profiler;
+        move |browser_id| {
+            if let Some(ref tabs) = tabs {
+                tabs.add(browser_id);
+            }
+            // Adding the first tab normally switches to it already
+            if window.selected_browser().is_none() {
+                select_browser(&servo, &window, browser_id);
+            }
+            if let Some(ref content) = html {
+                html::load_html(&mut servo.borrow_mut(), browser_id, content, base_url.as_ref());
+            }
+            // --wr-profiler turns the profiler on right after the first browser is created
+            if wr_profiler {
+                let event = WindowEvent::ToggleWebRenderDebug(WebRenderDebugOption::Profiler);
                 servo.borrow_mut().handle_events(vec![event]);
             }
-            Inhibit(false)
+            screenshot_browser.set(Some(browser_id));
+            // Servo may have loaded the page before telling which browser it's in
+            if let Some(succeeded) = window.load_outcome(browser_id) {
+                if let Some(ref loaded) = *window.loaded.borrow() {
+                    loaded(browser_id, succeeded);
+                }
+            }
+        }
+    };
+    new_browser(servo, window, url, created);
+}
+
+// URLs from other instances get their own tab. Without tabs (in kiosk
+// mode) they replace the page.
+fn url_opener(servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>, tabs: &Option<Rc<Tabs>>,
+              load_in_page: &UrlHandler) -> Rc<Fn(ServoUrl)> {
+    let servo = servo.clone();
+    let window = window.clone();
+    let tabs = tabs.clone();
+    let load_in_page = load_in_page.clone();
+    Rc::new(move |url| {
+        match tabs {
+            Some(ref tabs) => {
+                let tabs = tabs.clone();
+                // Adding a tab selects it
+                new_browser(&servo, &window, url, move |browser_id| tabs.add(browser_id));
+            },
+            None => if let Some(ref load_url) = *load_in_page.borrow() {
+                load_url(url);
+            },
+        }
+    })
+}
+
+// Dragging the window's border resizes the GLArea on every frame. Only the
+// size it settles on is sent, once no resize happened for a while.
+fn connect_resize(gl_area: &GLArea, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>) {
+    let servo = servo.clone();
+    let window = window.clone();
+    let resizes = Rc::new(Cell::new(0u32));
+    gl_area.connect_resize(move |_, _, _| {
+        let resize = resizes.get().wrapping_add(1);
+        resizes.set(resize);
+        let servo = servo.clone();
+        let window = window.clone();
+        let resizes = resizes.clone();
+        gtk::timeout_add(RESIZE_DEBOUNCE_MS, move || {
+            if resizes.get() == resize {
+                let event = WindowEvent::Resize(window.framebuffer_size());
+                servo.borrow_mut().handle_events(vec![event]);
+            }
+            Continue(false)
         });
+    });
+}
+
+// Saves the browser's page once it has loaded and had time to paint, then
+// closes the window. Fails if the page doesn't load, or takes too long to.
+fn capture_when_loaded(window: &Rc<ServoWindow>, path: &str, browser: Rc<Cell<Option<BrowserId>>>,
+                       exit_code: ExitCode) {
+    let capturing = Rc::new(Cell::new(false));
+    let loaded: Box<Fn(BrowserId, bool)> = {
+        let window = window.clone();
+        let path = path.to_owned();
+        let capturing = capturing.clone();
+        let exit_code = exit_code.clone();
+        Box::new(move |browser_id, succeeded| {
+            if capturing.get() || browser.get() != Some(browser_id) {
+                return;
+            }
+            capturing.set(true);
+            // The error page would be captured instead
+            if !succeeded {
+                eprintln!("The page failed to load, no screenshot was saved");
+                exit_code.set(1);
+                window.gtk_window.close();
+                return;
+            }
+            let window = window.clone();
+            let path = path.clone();
+            let exit_code = exit_code.clone();
+            gtk::timeout_add(SCREENSHOT_SETTLE_MS, move || {
+                if window.load_outcome(browser_id) == Some(false) {
+                    eprintln!("The page failed to load, no screenshot was saved");
+                    exit_code.set(1);
+                } else if let Err(error) = screenshot::save_to_file(&window, &path) {
+                    eprintln!("Can't save the screenshot to {}: {}", path, error);
+                    exit_code.set(1);
+                }
+                window.gtk_window.close();
+                Continue(false)
+            });
+        })
+    };
+    *window.loaded.borrow_mut() = Some(loaded);
+
+    let gtk_window = window.gtk_window.clone();
+    gtk::timeout_add_seconds(SCREENSHOT_TIMEOUT_S, move || {
+        if !capturing.get() {
+            eprintln!("The page didn't load within {}s, no screenshot was saved", SCREENSHOT_TIMEOUT_S);
+            exit_code.set(1);
+            gtk_window.close();
+        }
+        Continue(false)
+    });
+}
+
+// Without an explicit allowlist, kiosk pages stay on the homepage's origin
+fn kiosk_allowlist(options: &Options) -> Vec<ServoUrl> {
+    if !options.kiosk_allow.is_empty() {
+        return options.kiosk_allow.clone();
+    }
+    let homepage = homepage(options);
+    match homepage.as_url().join("/") {
+        Ok(origin) => vec![ServoUrl::from_url(origin)],
+        Err(_) => vec![homepage],
     }
+}
+
+// Same scheme, host and port, and a path under the allowed one, which ends
+// in a slash. The allowed directory itself can be named without it.
+fn is_allowed(url: &ServoUrl, allowed: &ServoUrl) -> bool {
+    let (url, allowed) = (url.as_url(), allowed.as_url());
+    url.scheme() == allowed.scheme() &&
+        url.host_str() == allowed.host_str() &&
+        url.port_or_known_default() == allowed.port_or_known_default() &&
+        (url.path().starts_with(allowed.path()) || format!("{}/", url.path()) == allowed.path())
+}
+
+// Zooms the selected page. Stops at the limits instead of stepping past them.
+fn zoom_by(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, factor: f32) {
+    let browser_id = match window.selected_browser() {
+        Some(browser_id) => browser_id,
+        None => return,
+    };
+    let current = window.zoom(browser_id);
+    let zoomed = (current * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+    if zoomed == current {
+        return;
+    }
+    window.set_zoom(browser_id, zoomed);
+    servo.borrow_mut().handle_events(vec![WindowEvent::Zoom(zoomed / current)]);
+}
+
+// Pages that aren't shown are only zoomed once they are selected again
+fn reset_zoom(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, browser_id: BrowserId) {
+    window.set_zoom(browser_id, 1.0);
+    if window.selected_browser() == Some(browser_id) {
+        servo.borrow_mut().handle_events(vec![WindowEvent::ResetZoom]);
+    }
+}
+
+// Nothing is sent when there's no page to go to, saving a round trip to the constellation
+fn traverse_history(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, direction: TraversalDirection) {
+    if let Some(browser_id) = window.selected_browser() {
+        if window.can_traverse(&direction) {
+            servo.borrow_mut().handle_events(vec![WindowEvent::Navigation(browser_id, direction)]);
+        }
+    }
+}
 
-    gtk::main();
+// The constellation's answer is waited for on another thread, which then
+// wakes the main loop up. created runs on the main loop, once the browser exists.
+fn new_browser<F>(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, url: ServoUrl, created: F)
+    where F: Fn(BrowserId) + 'static
+{
+    let (sender, receiver) = match ipc::channel() {
+        Ok(channel) => channel,
+        Err(error) => {
+            eprintln!("Can't create a browser for {}: {}", url, error);
+            return;
+        },
+    };
+    let (created_sender, created_receiver) = mpsc::channel();
+    let waker = window.waker.clone();
+    let thread_url = url.clone();
+    let spawned = thread::Builder::new().name("NewBrowser".to_owned()).spawn(move || {
+        match receiver.recv() {
+            Ok(browser_id) => {
+                created_sender.send(browser_id).ok();
+            },
+            Err(error) => eprintln!("Can't create a browser for {}: {}", thread_url, error),
+        }
+        // Also wakes up on errors, to forget the browser
+        drop(created_sender);
+        waker.wake();
+    });
+    if let Err(error) = spawned {
+        eprintln!("Can't create a browser for {}: {}", url, error);
+        return;
+    }
+    window.pending_browsers.borrow_mut().push(PendingBrowser {
+        receiver: created_receiver,
+        created: Box::new(created),
+    });
+    servo.borrow_mut().handle_events(vec![WindowEvent::NewBrowser(url, sender)]);
+}
+
+// Servo's zoom applies to whatever is shown, so each page's is put back
+// when it's selected
+fn select_browser(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, browser_id: BrowserId) {
+    let previous_zoom = window.selected_browser().map_or(1.0, |selected| window.zoom(selected));
+    window.select(browser_id);
+    let mut events = vec![WindowEvent::SelectBrowser(browser_id)];
+    let zoom = window.zoom(browser_id);
+    if zoom != previous_zoom {
+        events.push(WindowEvent::ResetZoom);
+        if zoom != 1.0 {
+            events.push(WindowEvent::Zoom(zoom));
+        }
+    }
+    servo.borrow_mut().handle_events(events);
+}
+
+fn device_pixel_ratio() -> Option<f32> {
+    let value = match env::var(DEVICE_PIXEL_RATIO_VAR) {
+        Ok(value) => value,
+        Err(_) => return None,
+    };
+    match value.parse() {
+        Ok(ratio) if ratio > 0.0 => Some(ratio),
+        _ => {
+            eprintln!("Invalid {}: {} (expected a positive number)", DEVICE_PIXEL_RATIO_VAR, value);
+            None
+        },
+    }
+}
+
+fn homepage(options: &Options) -> ServoUrl {
+    options.url.clone().unwrap_or_else(|| ServoUrl::parse(DEFAULT_URL).unwrap())
+}
+
+fn destroy_once(gtk_window: &Window, closed: &Cell<bool>) {
+    if !closed.get() {
+        closed.set(true);
+        gtk_window.destroy();
+    }
 }
 
 pub struct GtkEventLoopWaker {
@@ -201,26 +1125,280 @@ impl EventLoopWaker for GtkEventLoopWaker {
     }
 }
 
+// A browser the constellation was asked for
+struct PendingBrowser {
+    receiver: mpsc::Receiver<BrowserId>,
+    created: Box<Fn(BrowserId)>,
+}
+
+// What the window shows about each browser
+#[derive(Default)]
+struct PageState {
+    loading: bool,
+    // None until the page being loaded sends its title, or if it has none
+    title: Option<String>,
+    // Session history URLs and the index of the current one
+    history: Vec<ServoUrl>,
+    current: usize,
+    // Set by the first load end, and by the first load error
+    loaded: bool,
+    failed: bool,
+    // Shown again when the browser's tab is selected
+    favicon: Option<ServoUrl>,
+    status: Option<String>,
+    // Servo only knows relative zoom steps, the total is kept here to clamp
+    // it. None at 100%.
+    zoom: Option<f32>,
+}
+
+impl PageState {
+    fn current_url(&self) -> Option<ServoUrl> {
+        self.history.get(self.current).cloned()
+    }
+
+    // Empty for untitled pages, which leaves the window untitled
+    fn window_title(&self) -> String {
+        match (&self.title, self.loading) {
+            (&Some(ref title), _) => title.clone(),
+            (&None, true) => "Loading…".to_owned(),
+            (&None, false) => String::new(),
+        }
+    }
+
+    // Untitled pages show their address, a tab needs some label
+    fn tab_title(&self) -> String {
+        match self.title {
+            None if !self.loading => self.current_url().map(|url| url.as_str().to_owned()).unwrap_or_default(),
+            _ => self.window_title(),
+        }
+    }
+}
+
 struct ServoWindow {
     // All these fields will be used in WindowMethods implementations
     gl_area: GLArea,
     gtk_window: Window,
+    url_bar: Option<Rc<UrlBar>>,
     waker: Box<EventLoopWaker>,
     gl: Rc<gl::Gl>,
+    error_bar: Option<Rc<ErrorBar>>,
+    render_fallback: Rc<RenderFallback>,
+    splash: Option<Rc<Splash>>,
+    status_bar: Option<Rc<StatusBar>>,
+    // Only the selected browser's state is shown
+    selected_browser: Cell<Option<BrowserId>>,
+    pages: RefCell<HashMap<BrowserId, PageState>>,
+    // Pages may only navigate under these URLs, anything goes if None
+    navigation_allowlist: Option<Vec<ServoUrl>>,
+    // When set, other schemes are only followed once the user agrees
+    confirmed_schemes: Option<Vec<String>>,
+    logical_size: Option<(u32, u32)>,
+    // From the environment, read once so servo always gets the same ratio
+    device_pixel_ratio: Option<f32>,
+    // Pages can't resize, move or leave fullscreen a kiosk window
+    kiosk: bool,
+    benchmark: Option<Rc<Benchmark>>,
+    // Set once servo is running, sends events from callbacks, where servo
+    // is already borrowed
+    event_sender: RefCell<Option<Box<Fn(WindowEvent)>>>,
+    // Set once servo is running, must not use servo synchronously
+    unhandled_key: RefCell<Option<Box<Fn(Key, KeyModifiers)>>>,
+    pending_browsers: RefCell<Vec<PendingBrowser>>,
+    // Set with --screenshot, called whenever a page has loaded (true) or
+    // failed to (false)
+    loaded: RefCell<Option<Box<Fn(BrowserId, bool)>>>,
+    // Told whenever the title shown for a page may change, after the window
+    // shows it. Called from handle_events, must not use servo synchronously.
+    title_observer: RefCell<Option<Box<Fn(BrowserId, Option<String>)>>>,
+    // Told whenever a page's zoom changes
+    zoom_observer: RefCell<Option<Box<Fn(BrowserId, f32)>>>,
+    favicons: FaviconLoader,
+    cursors: RefCell<HashMap<&'static str, gdk::Cursor>>,
+    // Ctrl+Shift+A starts it, the pointer drags it out. Its crosshair
+    // replaces the page's cursor until it ends.
+    area_selection: Rc<AreaSelection>,
+}
+
+impl ServoWindow {
+    // None until the first browser is created
+    fn selected_browser(&self) -> Option<BrowserId> {
+        self.selected_browser.get()
+    }
+
+    fn select(&self, browser_id: BrowserId) {
+        self.selected_browser.set(Some(browser_id));
+        self.show_page_state(browser_id);
+        // Unlike the rest of the page state, these only change when servo says so
+        let (favicon, status) = match self.pages.borrow().get(&browser_id) {
+            Some(page) => (page.favicon.clone(), page.status.clone()),
+            None => (None, None),
+        };
+        if let Some(ref status_bar) = self.status_bar {
+            status_bar.set_text(status.as_ref().map(|status| status.as_str()));
+        }
+        match favicon {
+            Some(url) => self.favicons.load(url),
+            None => self.favicons.clear(),
+        }
+    }
+
+    fn tab_title(&self, browser_id: BrowserId) -> String {
+        self.pages.borrow().get(&browser_id).map(|page| page.tab_title()).unwrap_or_default()
+    }
+
+    fn zoom(&self, browser_id: BrowserId) -> f32 {
+        self.pages.borrow().get(&browser_id).and_then(|page| page.zoom).unwrap_or(1.0)
+    }
+
+    // Doesn't zoom the page, servo has to be told
+    fn set_zoom(&self, browser_id: BrowserId, zoom: f32) {
+        let zoom = if zoom == 1.0 { None } else { Some(zoom) };
+        self.pages.borrow_mut().entry(browser_id).or_insert_with(PageState::default).zoom = zoom;
+        if let Some(ref observer) = *self.zoom_observer.borrow() {
+            observer(browser_id, zoom.unwrap_or(1.0));
+        }
+    }
+
+    // Whether the browser's first load succeeded, None while it hasn't ended
+    fn load_outcome(&self, browser_id: BrowserId) -> Option<bool> {
+        match self.pages.borrow().get(&browser_id) {
+            Some(page) if page.failed => Some(false),
+            Some(page) if page.loaded => Some(true),
+            _ => None,
+        }
+    }
+
+    fn current_url(&self) -> Option<ServoUrl> {
+        let pages = self.pages.borrow();
+        self.selected_browser().and_then(|browser_id| pages.get(&browser_id)).and_then(|page| page.current_url())
+    }
+
+    // Browsers the constellation answered for since the last call, with
+    // what to do with them. Failed ones are dropped.
+    fn take_created_browsers(&self) -> Vec<(BrowserId, Box<Fn(BrowserId)>)> {
+        let mut pending_browsers = self.pending_browsers.borrow_mut();
+        let mut created = vec![];
+        let mut still_pending = vec![];
+        for browser in pending_browsers.drain(..) {
+            match browser.receiver.try_recv() {
+                Ok(browser_id) => created.push((browser_id, browser.created)),
+                Err(TryRecvError::Empty) => still_pending.push(browser),
+                Err(TryRecvError::Disconnected) => {},
+            }
+        }
+        *pending_browsers = still_pending;
+        created
+    }
+
+    // Sends the event on the next main loop iteration
+    fn send_later(&self, event: WindowEvent) {
+        match *self.event_sender.borrow() {
+            Some(ref send) => send(event),
+            None => eprintln!("Servo isn't running, dropping event {:?}", event),
+        }
+    }
+
+    // Updates the window's title, address bar and spinner if it's the
+    // selected browser, then tells the title observer
+    fn show_page_state(&self, id: BrowserId) {
+        let title = {
+            let pages = self.pages.borrow();
+            let page = match pages.get(&id) {
+                Some(page) => page,
+                None => return,
+            };
+            if self.selected_browser.get() == Some(id) {
+                self.gtk_window.set_title(&page.window_title());
+                if let Some(ref url_bar) = self.url_bar {
+                    url_bar.set_loading(page.loading);
+                    if let Some(url) = page.current_url() {
+                        url_bar.set_url(&url);
+                    }
+                }
+            }
+            page.title.clone()
+        };
+        if let Some(ref observer) = *self.title_observer.borrow() {
+            observer(id, title);
+        }
+    }
+
+    fn can_traverse(&self, direction: &TraversalDirection) -> bool {
+        let pages = self.pages.borrow();
+        let page = match self.selected_browser().and_then(|browser_id| pages.get(&browser_id)) {
+            Some(page) => page,
+            None => return false,
+        };
+        match *direction {
+            TraversalDirection::Back(steps) => page.current >= steps,
+            TraversalDirection::Forward(steps) => page.current + steps < page.history.len(),
+        }
+    }
+
+    // The page only gets the GLArea, not the whole window: bars shown above or
+    // below it shrink the viewport, and the GLArea's resize handler tells servo.
+    // With a fixed logical size, the GLArea is kept at that size and centered.
+    fn page_size(&self) -> (u32, u32) {
+        match self.logical_size {
+            Some(size) => size,
+            None => (self.gl_area.get_allocated_width() as u32, self.gl_area.get_allocated_height() as u32),
+        }
+    }
+
+    // Device pixels (the GL buffer's) per logical pixel (GDK's)
+    fn buffer_scale(&self) -> f32 {
+        self.gtk_window.get_scale_factor() as f32
+    }
+
+    // GDK reports positions in logical pixels, servo wants device ones
+    fn device_point(&self, position: (f64, f64)) -> TypedPoint2D<f32, DevicePixel> {
+        let scale_factor = self.buffer_scale() as f64;
+        TypedPoint2D::new((position.0 * scale_factor) as f32, (position.1 * scale_factor) as f32)
+    }
+
+    // Servo asks for a cursor on every mouse move, cursors are only created once
+    fn set_cursor_name(&self, cursor_name: &'static str) {
+        let mut cursors = self.cursors.borrow_mut();
+        let cursor = cursors.entry(cursor_name).or_insert_with(|| {
+            let display = Display::get_default().unwrap();
+            gdk::Cursor::new_from_name(&display, cursor_name)
+        });
+        let window = self.gtk_window.get_window().unwrap();
+        gdk::WindowExt::set_cursor(&window, &*cursor);
+    }
 }
 
 impl WindowMethods for ServoWindow {
     fn prepare_for_composite(&self, _width: usize, _height: usize) -> bool {
         self.gl_area.make_current();
+        // GTK only binds the GLArea's framebuffer when it draws, which it
+        // never does for headless screenshots
+        self.gl_area.attach_buffers();
+        if let Some(error) = self.gl_area.get_error() {
+            self.render_fallback.failed(&format!("can't make the GL context current: {}", error));
+            return false;
+        }
         true
     }
 
+    // Servo only presents frames it could prepare. glGetError isn't checked:
+    // it would sync with the GPU every frame, and report errors left by
+    // unrelated GL calls.
     fn present(&self) {
+        self.render_fallback.succeeded();
+        if let Some(ref splash) = self.splash {
+            splash.frame_presented();
+        }
+        if let Some(ref benchmark) = self.benchmark {
+            benchmark.frame_presented();
+        }
         self.gl_area.queue_render();
     }
 
+    // Servo then talks to the system clipboard itself, the embedder has no
+    // clipboard callbacks to implement
     fn supports_clipboard(&self) -> bool {
-        false
+        true
     }
 
     fn create_event_loop_waker(&self) -> Box<EventLoopWaker> {
@@ -232,22 +1410,25 @@ impl WindowMethods for ServoWindow {
     }
 
     fn hidpi_factor(&self) -> ScaleFactor<f32, DeviceIndependentPixel, DevicePixel> {
-        ScaleFactor::new(self.gtk_window.get_scale_factor() as f32)
+        ScaleFactor::new(self.device_pixel_ratio.unwrap_or_else(|| self.buffer_scale()))
     }
 
     fn framebuffer_size(&self) -> TypedSize2D<u32, DevicePixel> {
-        let (width, height) = self.gtk_window.get_size();
-        let scale_factor = self.gtk_window.get_scale_factor() as u32;
-        TypedSize2D::new(scale_factor * width as u32, scale_factor * height as u32)
+        let (width, height) = self.page_size();
+        let scale_factor = self.buffer_scale();
+        TypedSize2D::new((scale_factor * width as f32).round() as u32, (scale_factor * height as f32).round() as u32)
     }
 
     fn window_rect(&self) -> TypedRect<u32, DevicePixel> {
         TypedRect::new(TypedPoint2D::new(0, 0), self.framebuffer_size())
     }
 
+    // The viewport in CSS pixels, smaller than the page's logical size when
+    // the device pixel ratio is overridden upwards
     fn size(&self) -> TypedSize2D<f32, DeviceIndependentPixel> {
-        let (width, height) = self.gtk_window.get_size();
-        TypedSize2D::new(width as f32, height as f32)
+        let framebuffer_size = self.framebuffer_size();
+        let hidpi_factor = self.hidpi_factor().get();
+        TypedSize2D::new(framebuffer_size.width as f32 / hidpi_factor, framebuffer_size.height as f32 / hidpi_factor)
     }
 
     fn client_window(&self, _id: BrowserId) -> (Size2D<u32>, Point2D<i32>) {
@@ -256,58 +1437,218 @@ impl WindowMethods for ServoWindow {
         (Size2D::new(width as u32, height as u32), Point2D::new(x as i32, y as i32))
     }
 
-    fn set_page_title(&self, _id: BrowserId, title: Option<String>) {
-        self.gtk_window.set_title(match title {
-            Some(ref title) => title,
-            None => "",
-        });
+    fn set_page_title(&self, id: BrowserId, title: Option<String>) {
+        self.pages.borrow_mut().entry(id).or_insert_with(PageState::default).title = title;
+        self.show_page_state(id);
     }
 
-    fn allow_navigation(&self, _id: BrowserId, _url: ServoUrl, chan: ipc::IpcSender<bool>) {
-        chan.send(true).ok();
+    fn allow_navigation(&self, _id: BrowserId, url: ServoUrl, chan: ipc::IpcSender<bool>) {
+        let allowed = match self.navigation_allowlist {
+            Some(ref allowlist) => allowlist.iter().any(|allowed| is_allowed(&url, allowed)),
+            None => true,
+        };
+        if !allowed {
+            eprintln!("Navigation to {} blocked", url);
+            chan.send(false).ok();
+            return;
+        }
+        let confirmed = match self.confirmed_schemes {
+            Some(ref schemes) => schemes.iter().any(|scheme| scheme == url.scheme()),
+            None => true,
+        };
+        if confirmed {
+            chan.send(true).ok();
+            return;
+        }
+
+        // Servo waits for the answer, not the GTK thread
+        let dialog = MessageDialog::new(
+            Some(&self.gtk_window),
+            DIALOG_MODAL | DIALOG_DESTROY_WITH_PARENT,
+            MessageType::Question,
+            ButtonsType::YesNo,
+            &format!("Open {}?", url),
+        );
+        let chan = RefCell::new(Some(chan));
+        dialog.connect_response(move |dialog, response| {
+            let allowed = response == ResponseType::Yes.into();
+            if let Some(chan) = chan.borrow_mut().take() {
+                chan.send(allowed).ok();
+            }
+            dialog.destroy();
+        });
+        dialog.show_all();
     }
 
-    fn set_inner_size(&self, _id: BrowserId, _size: Size2D<u32>) {
+    // Sizes and positions come in device pixels, GTK wants logical ones
+    fn set_inner_size(&self, _id: BrowserId, size: Size2D<u32>) {
+        if self.kiosk {
+            return;
+        }
+        let scale_factor = self.buffer_scale();
+        // The page only gets the GLArea, the bars around it keep their size
+        let (window_width, window_height) = self.gtk_window.get_size();
+        let chrome_width = window_width - self.gl_area.get_allocated_width();
+        let chrome_height = window_height - self.gl_area.get_allocated_height();
+        self.gtk_window.resize((size.width as f32 / scale_factor) as i32 + chrome_width,
+                               (size.height as f32 / scale_factor) as i32 + chrome_height);
     }
 
-    fn set_position(&self, _id: BrowserId, _point: Point2D<i32>) {
+    fn set_position(&self, _id: BrowserId, point: Point2D<i32>) {
+        if self.kiosk {
+            return;
+        }
+        let scale_factor = self.buffer_scale();
+        self.gtk_window.move_((point.x as f32 / scale_factor) as i32, (point.y as f32 / scale_factor) as i32);
     }
 
-    fn set_fullscreen_state(&self, _id: BrowserId, _state: bool) {
+    // GTK restores the windowed size and position when leaving fullscreen
+    fn set_fullscreen_state(&self, _id: BrowserId, state: bool) {
+        if state {
+            self.gtk_window.fullscreen();
+        } else if !self.kiosk {
+            self.gtk_window.unfullscreen();
+        }
     }
 
-    fn status(&self, _id: BrowserId, _status: Option<String>) {
+    // Like every WindowMethods callback, called on the GTK thread from handle_events
+    fn status(&self, id: BrowserId, status: Option<String>) {
+        self.pages.borrow_mut().entry(id).or_insert_with(PageState::default).status = status.clone();
+        if self.selected_browser.get() != Some(id) {
+            return;
+        }
+        if let Some(ref status_bar) = self.status_bar {
+            status_bar.set_text(status.as_ref().map(|status| status.as_str()));
+        }
     }
 
-    fn load_start(&self, _id: BrowserId) {
+    fn load_start(&self, id: BrowserId) {
+        {
+            let mut pages = self.pages.borrow_mut();
+            let page = pages.entry(id).or_insert_with(PageState::default);
+            page.loading = true;
+            page.title = None;
+        }
+        self.show_page_state(id);
     }
 
-    fn load_end(&self, _id: BrowserId) {
+    fn load_end(&self, id: BrowserId) {
+        {
+            let mut pages = self.pages.borrow_mut();
+            let page = pages.entry(id).or_insert_with(PageState::default);
+            page.loading = false;
+            page.loaded = true;
+        }
+        self.show_page_state(id);
+        // Whatever happened, there's something to show by now
+        if let Some(ref splash) = self.splash {
+            splash.dismiss();
+        }
+        if let Some(ref benchmark) = self.benchmark {
+            benchmark.load_ended();
+        }
+        if let Some(ref loaded) = *self.loaded.borrow() {
+            loaded(id, true);
+        }
     }
 
-    fn load_error(&self, _id: BrowserId, _: NetError, _url: String) {
+    fn load_error(&self, id: BrowserId, error: NetError, url: String) {
+        self.pages.borrow_mut().entry(id).or_insert_with(PageState::default).failed = true;
+        if let Some(ref loaded) = *self.loaded.borrow() {
+            loaded(id, false);
+        }
+        let error = format!("{:?}", error);
+        // Benchmarks record the failure and move on, timing an error page
+        // would skew the results
+        if let Some(ref benchmark) = self.benchmark {
+            benchmark.load_failed(&error);
+            return;
+        }
+        if let Some(ref error_bar) = self.error_bar {
+            error_bar.report(&format!("Failed to load {}", url), &format!("Network error: {}", error));
+        }
+        // The error page is a data: URL, if it fails too there's nothing better to show
+        if !url.starts_with("data:") {
+            let error_url = html::html_to_data_url(&html::error_page(&url, &error), None);
+            self.send_later(WindowEvent::LoadUrl(id, error_url));
+        }
     }
 
     fn head_parsed(&self, _id: BrowserId) {
+        if let Some(ref splash) = self.splash {
+            splash.expect_content();
+        }
+        if let Some(ref benchmark) = self.benchmark {
+            benchmark.head_parsed();
+        }
     }
 
-    fn history_changed(&self, _id: BrowserId, _entries: Vec<LoadData>, _current: usize) {
+    fn history_changed(&self, id: BrowserId, entries: Vec<LoadData>, current: usize) {
+        {
+            let mut pages = self.pages.borrow_mut();
+            let page = pages.entry(id).or_insert_with(PageState::default);
+            page.history = entries.into_iter().map(|entry| entry.url).collect();
+            page.current = current;
+        }
+        self.show_page_state(id);
     }
 
+    // GDK uses the CSS cursor names
     fn set_cursor(&self, cursor: Cursor) {
+        if self.area_selection.active.get() {
+            return;
+        }
         let cursor_name = match cursor {
+            Cursor::None => "none",
+            Cursor::Default => "default",
             Cursor::Pointer => "pointer",
-            _ => "default",
+            Cursor::ContextMenu => "context-menu",
+            Cursor::Help => "help",
+            Cursor::Progress => "progress",
+            Cursor::Wait => "wait",
+            Cursor::Cell => "cell",
+            Cursor::Crosshair => "crosshair",
+            Cursor::Text => "text",
+            Cursor::VerticalText => "vertical-text",
+            Cursor::Alias => "alias",
+            Cursor::Copy => "copy",
+            Cursor::Move => "move",
+            Cursor::NoDrop => "no-drop",
+            Cursor::NotAllowed => "not-allowed",
+            Cursor::Grab => "grab",
+            Cursor::Grabbing => "grabbing",
+            Cursor::EResize => "e-resize",
+            Cursor::NResize => "n-resize",
+            Cursor::NeResize => "ne-resize",
+            Cursor::NwResize => "nw-resize",
+            Cursor::SResize => "s-resize",
+            Cursor::SeResize => "se-resize",
+            Cursor::SwResize => "sw-resize",
+            Cursor::WResize => "w-resize",
+            Cursor::EwResize => "ew-resize",
+            Cursor::NsResize => "ns-resize",
+            Cursor::NeswResize => "nesw-resize",
+            Cursor::NwseResize => "nwse-resize",
+            Cursor::ColResize => "col-resize",
+            Cursor::RowResize => "row-resize",
+            Cursor::AllScroll => "all-scroll",
+            Cursor::ZoomIn => "zoom-in",
+            Cursor::ZoomOut => "zoom-out",
         };
-        let display = Display::get_default().unwrap();
-        let cursor = gdk::Cursor::new_from_name(&display, cursor_name);
-        let window = self.gtk_window.get_window().unwrap();
-        gdk::WindowExt::set_cursor(&window, &cursor);
+        self.set_cursor_name(cursor_name);
     }
 
-    fn set_favicon(&self, _id: BrowserId, _url: ServoUrl) {
+    fn set_favicon(&self, id: BrowserId, url: ServoUrl) {
+        self.pages.borrow_mut().entry(id).or_insert_with(PageState::default).favicon = Some(url.clone());
+        if self.selected_browser.get() == Some(id) {
+            self.favicons.load(url);
+        }
     }
 
-    fn handle_key(&self, _id: Option<BrowserId>, _ch: Option<char>, _key: Key, _mods: KeyModifiers) {
+    // Keys the page didn't handle
+    fn handle_key(&self, _id: Option<BrowserId>, _ch: Option<char>, key: Key, mods: KeyModifiers) {
+        if let Some(ref unhandled_key) = *self.unhandled_key.borrow() {
+            unhandled_key(key, mods);
+        }
     }
 }
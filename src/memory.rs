@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::io::Read;
+
+use gtk::{self, Align, Continue, Label, LabelExt, WidgetExt};
+use libc;
+
+const REFRESH_INTERVAL_S: u32 = 2;
+
+// Resident memory of the whole process (servo's threads included), in bytes
+pub fn resident_memory() -> Option<u64> {
+    let mut statm = String::new();
+    if File::open("/proc/self/statm").and_then(|mut file| file.read_to_string(&mut statm)).is_err() {
+        return None;
+    }
+    // statm lists sizes in pages: total, resident, shared, ...
+    let pages: u64 = match statm.split_whitespace().nth(1).and_then(|pages| pages.parse().ok()) {
+        Some(pages) => pages,
+        None => return None,
+    };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some(pages * page_size as u64)
+}
+
+// A label showing the resident memory, refreshed every few seconds while visible
+pub fn memory_label(visible: bool) -> Label {
+    let label = Label::new(None);
+    label.set_halign(Align::End);
+    // For themes
+    if let Some(context) = label.get_style_context() {
+        context.add_class("memory");
+    }
+    label.set_no_show_all(true);
+    label.set_visible(visible);
+    refresh(&label);
+
+    let timer_label = label.clone();
+    gtk::timeout_add_seconds(REFRESH_INTERVAL_S, move || {
+        if timer_label.get_visible() {
+            refresh(&timer_label);
+        }
+        Continue(true)
+    });
+    label
+}
+
+pub fn refresh(label: &Label) {
+    match resident_memory() {
+        Some(bytes) => label.set_text(&format!("Memory: {:.1} MB", bytes as f64 / (1024.0 * 1024.0))),
+        None => label.set_text("Memory: unknown"),
+    }
+}
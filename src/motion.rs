@@ -0,0 +1,55 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::{self, Continue};
+use servo::Servo;
+use servo::compositing::windowing::WindowEvent;
+
+use FRAME_INTERVAL_MS;
+use ServoWindow;
+
+// High polling rate mice report many positions per frame. Only the latest
+// one is sent to servo, once per frame.
+pub struct MotionCoalescer {
+    servo: Rc<RefCell<Servo<ServoWindow>>>,
+    window: Rc<ServoWindow>,
+    pending: Rc<Cell<Option<(f64, f64)>>>,
+}
+
+impl MotionCoalescer {
+    pub fn new(servo: Rc<RefCell<Servo<ServoWindow>>>, window: Rc<ServoWindow>) -> MotionCoalescer {
+        MotionCoalescer {
+            servo,
+            window,
+            pending: Rc::new(Cell::new(None)),
+        }
+    }
+
+    pub fn moved(&self, x: f64, y: f64) {
+        let scheduled = self.pending.get().is_some();
+        self.pending.set(Some((x, y)));
+        if scheduled {
+            return;
+        }
+        let servo = self.servo.clone();
+        let window = self.window.clone();
+        let pending = self.pending.clone();
+        gtk::timeout_add(FRAME_INTERVAL_MS, move || {
+            send_pending(&servo, &window, &pending);
+            Continue(false)
+        });
+    }
+
+    // Other pointer events must not overtake a pending move
+    pub fn flush(&self) {
+        send_pending(&self.servo, &self.window, &self.pending);
+    }
+}
+
+fn send_pending(servo: &RefCell<Servo<ServoWindow>>, window: &ServoWindow, pending: &Cell<Option<(f64, f64)>>) {
+    if let Some(position) = pending.get() {
+        pending.set(None);
+        let event = WindowEvent::MouseWindowMoveEventClass(window.device_point(position));
+        servo.borrow_mut().handle_events(vec![event]);
+    }
+}
@@ -0,0 +1,387 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use servo::servo_url::ServoUrl;
+use url::Url;
+
+use scroll::Easing;
+
+// Schemes --confirm-navigation doesn't ask about. about: is used by new tabs.
+const SAFE_SCHEMES: &[&str] = &["http", "https", "data", "about"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAntialiasing {
+    None,
+    Grayscale,
+    Subpixel,
+}
+
+impl TextAntialiasing {
+    fn from_name(name: &str) -> Option<TextAntialiasing> {
+        match name {
+            "none" => Some(TextAntialiasing::None),
+            "grayscale" => Some(TextAntialiasing::Grayscale),
+            "subpixel" => Some(TextAntialiasing::Subpixel),
+            _ => None,
+        }
+    }
+}
+
+// What closing the last remaining tab does (Ctrl+W or its close button)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LastTabClose {
+    CloseWindow,
+    Homepage,
+}
+
+impl LastTabClose {
+    fn from_name(name: &str) -> Option<LastTabClose> {
+        match name {
+            "close" => Some(LastTabClose::CloseWindow),
+            "homepage" => Some(LastTabClose::Homepage),
+            _ => None,
+        }
+    }
+}
+
+// Which tabs show their close button
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CloseButtons {
+    Always,
+    // Only while the pointer is over the tab
+    Hover,
+    // Only on the selected tab
+    Active,
+}
+
+impl CloseButtons {
+    fn from_name(name: &str) -> Option<CloseButtons> {
+        match name {
+            "always" => Some(CloseButtons::Always),
+            "hover" => Some(CloseButtons::Hover),
+            "active" => Some(CloseButtons::Active),
+            _ => None,
+        }
+    }
+}
+
+pub struct Options {
+    // Duration of embedder-driven smooth scrolls, 0 to jump instantly
+    pub scroll_duration_ms: u32,
+    pub scroll_easing: Easing,
+    // Raw HTML to load instead of a URL, from --html or --html-file
+    pub html: Option<String>,
+    pub base_url: Option<ServoUrl>,
+    // Show page errors in a bar above the page
+    pub error_bar: bool,
+    // Command used to edit local pages, and whether to reload them when saved
+    pub editor: Option<String>,
+    pub watch: bool,
+    // Show the process memory usage below the page
+    pub show_memory: bool,
+    // Show WebRender's profiler overlay from the start
+    pub wr_profiler: bool,
+    // Don't hand URLs over to an already running instance
+    pub new_instance: bool,
+    pub text_antialiasing: TextAntialiasing,
+    // Homepage, loaded when no URL is given as a positional argument
+    pub url: Option<ServoUrl>,
+    // Fullscreen, no browser UI, and navigation limited to what's under the
+    // allowed URLs (the homepage's origin if none are given)
+    pub kiosk: bool,
+    pub kiosk_allow: Vec<ServoUrl>,
+    // Ask before following a link to any other scheme than these
+    pub confirm_navigation: Option<Vec<String>>,
+    // Cover the page with a splash screen until it's painted
+    pub splash: bool,
+    // Lay pages out at this size whatever the window size is
+    pub logical_size: Option<(u32, u32)>,
+    pub last_tab_close: LastTabClose,
+    // Tabs share the tab strip's width, within these bounds (in pixels)
+    pub tab_min_width: u32,
+    pub tab_max_width: u32,
+    pub close_buttons: CloseButtons,
+    // How long (in seconds) closing a tab can be undone, 0 to not offer it
+    pub tab_close_undo_secs: u32,
+    // GTK CSS file, or the name of a built-in theme (light, dark)
+    pub theme: Option<String>,
+    // URLs to time, each loaded warmup + repeat times, and where to write
+    // the CSV report (stdout by default)
+    pub benchmark: Option<Vec<ServoUrl>>,
+    pub benchmark_warmup: u32,
+    pub benchmark_repeat: u32,
+    pub benchmark_output: Option<String>,
+    // Render the page without showing the window, save it to this image
+    // file once loaded, then quit
+    pub screenshot: Option<String>,
+    // Positional arguments
+    pub urls: Vec<String>,
+}
+
+impl Options {
+    pub fn from_args() -> Options {
+        let mut options = Options {
+            scroll_duration_ms: 200,
+            scroll_easing: Easing::EaseOut,
+            html: None,
+            base_url: None,
+            error_bar: true,
+            editor: None,
+            watch: false,
+            show_memory: false,
+            wr_profiler: false,
+            new_instance: false,
+            text_antialiasing: TextAntialiasing::Subpixel,
+            url: None,
+            kiosk: false,
+            kiosk_allow: vec![],
+            confirm_navigation: None,
+            splash: true,
+            logical_size: None,
+            last_tab_close: LastTabClose::CloseWindow,
+            tab_min_width: 100,
+            tab_max_width: 240,
+            close_buttons: CloseButtons::Always,
+            tab_close_undo_secs: 8,
+            theme: None,
+            benchmark: None,
+            benchmark_warmup: 1,
+            benchmark_repeat: 3,
+            benchmark_output: None,
+            screenshot: None,
+            urls: vec![],
+        };
+        let mut html_file_url = None;
+
+        for arg in env::args().skip(1) {
+            if arg == "--no-error-bar" {
+                options.error_bar = false;
+            } else if arg == "--show-memory" {
+                options.show_memory = true;
+            } else if arg == "--wr-profiler" {
+                options.wr_profiler = true;
+            } else if arg == "--no-splash" {
+                options.splash = false;
+            } else if arg == "--kiosk" {
+                options.kiosk = true;
+            } else if let Some(value) = flag_value(&arg, "--kiosk-allow") {
+                options.kiosk_allow.extend(value.split(',').filter_map(allowed_url));
+            } else if arg == "--confirm-navigation" {
+                options.confirm_navigation = Some(SAFE_SCHEMES.iter().map(|scheme| scheme.to_string()).collect());
+            } else if let Some(value) = flag_value(&arg, "--confirm-navigation") {
+                options.confirm_navigation = Some(value.split(',').map(|scheme| scheme.to_owned()).collect());
+            } else if let Some(value) = flag_value(&arg, "--url") {
+                match ServoUrl::parse(value) {
+                    Ok(url) => options.url = Some(url),
+                    Err(error) => eprintln!("Invalid URL {}: {}", value, error),
+                }
+            } else if arg == "--new-instance" {
+                options.new_instance = true;
+            } else if arg == "--watch" {
+                options.watch = true;
+            } else if let Some(value) = flag_value(&arg, "--editor") {
+                options.editor = Some(value.to_owned());
+            } else if let Some(value) = flag_value(&arg, "--scroll-duration") {
+                match value.parse() {
+                    Ok(ms) => options.scroll_duration_ms = ms,
+                    Err(_) => eprintln!("Invalid scroll duration: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--scroll-easing") {
+                match Easing::from_name(value) {
+                    Some(easing) => options.scroll_easing = easing,
+                    None => eprintln!("Unknown scroll easing: {} (expected linear, ease-out or ease-in-out)", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--html") {
+                options.html = Some(value.to_owned());
+            } else if let Some(value) = flag_value(&arg, "--html-file") {
+                match read_file(value) {
+                    Ok(html) => options.html = Some(html),
+                    Err(error) => eprintln!("Can't read {}: {}", value, error),
+                }
+                html_file_url = fs::canonicalize(value).ok().and_then(|path| file_url(&path));
+            } else if let Some(value) = flag_value(&arg, "--text-aa") {
+                match TextAntialiasing::from_name(value) {
+                    Some(mode) => options.text_antialiasing = mode,
+                    None => eprintln!("Unknown text antialiasing: {} (expected none, grayscale or subpixel)", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--logical-size") {
+                match parse_size(value) {
+                    Some(size) => options.logical_size = Some(size),
+                    None => eprintln!("Invalid size: {} (expected WIDTHxHEIGHT)", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--base-url") {
+                match ServoUrl::parse(value) {
+                    Ok(url) => options.base_url = Some(url),
+                    Err(error) => eprintln!("Invalid base URL {}: {}", value, error),
+                }
+            } else if let Some(value) = flag_value(&arg, "--last-tab-close") {
+                match LastTabClose::from_name(value) {
+                    Some(action) => options.last_tab_close = action,
+                    None => eprintln!("Unknown last tab close action: {} (expected close or homepage)", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--tab-min-width") {
+                match value.parse() {
+                    Ok(width) => options.tab_min_width = width,
+                    Err(_) => eprintln!("Invalid tab width: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--tab-max-width") {
+                match value.parse() {
+                    Ok(width) => options.tab_max_width = width,
+                    Err(_) => eprintln!("Invalid tab width: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--tab-close-button") {
+                match CloseButtons::from_name(value) {
+                    Some(close_buttons) => options.close_buttons = close_buttons,
+                    None => eprintln!("Unknown close button setting: {} (expected always, hover or active)", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--tab-close-undo") {
+                match value.parse() {
+                    Ok(seconds) => options.tab_close_undo_secs = seconds,
+                    Err(_) => eprintln!("Invalid tab close undo duration: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--theme") {
+                options.theme = Some(value.to_owned());
+            } else if let Some(value) = flag_value(&arg, "--benchmark") {
+                match read_file(value) {
+                    Ok(list) => {
+                        let urls = parse_url_list(&list);
+                        if urls.is_empty() {
+                            eprintln!("No URLs to benchmark in {}", value);
+                        } else {
+                            options.benchmark = Some(urls);
+                        }
+                    },
+                    Err(error) => eprintln!("Can't read {}: {}", value, error),
+                }
+            } else if let Some(value) = flag_value(&arg, "--benchmark-warmup") {
+                match value.parse() {
+                    Ok(count) => options.benchmark_warmup = count,
+                    Err(_) => eprintln!("Invalid warmup count: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--benchmark-repeat") {
+                match value.parse() {
+                    Ok(count) if count > 0 => options.benchmark_repeat = count,
+                    _ => eprintln!("Invalid repeat count: {}", value),
+                }
+            } else if let Some(value) = flag_value(&arg, "--benchmark-output") {
+                options.benchmark_output = Some(value.to_owned());
+            } else if let Some(value) = flag_value(&arg, "--screenshot") {
+                options.screenshot = Some(value.to_owned());
+            } else if arg.starts_with("--") {
+                eprintln!("Unknown option: {}", arg);
+            } else {
+                options.urls.push(arg);
+            }
+        }
+
+        // A running instance would get the URL, but not the options to do
+        // anything special with it: no report or screenshot would be written,
+        // the HTML wouldn't be loaded, and the page wouldn't be locked down
+        if options.screenshot.is_some() || options.benchmark.is_some() || options.html.is_some() || options.kiosk {
+            options.new_instance = true;
+        }
+
+        if options.tab_min_width > options.tab_max_width {
+            eprintln!("The minimum tab width is over the maximum, using {} for both", options.tab_min_width);
+            options.tab_max_width = options.tab_min_width;
+        }
+
+        // Relative resources of an HTML file resolve next to it, unless told otherwise
+        if options.base_url.is_none() {
+            options.base_url = html_file_url;
+        }
+
+        options
+    }
+}
+
+// Turns a positional argument into a URL: existing files are opened as
+// such, and anything without a scheme (example.com, localhost:8000) is
+// taken as an https:// address
+pub fn url_from_arg(arg: &str) -> Option<ServoUrl> {
+    if let Ok(path) = fs::canonicalize(arg) {
+        return file_url(&path);
+    }
+    let has_scheme = arg.contains("://") || arg.starts_with("about:") || arg.starts_with("data:");
+    let url = if has_scheme {
+        ServoUrl::parse(arg)
+    } else {
+        ServoUrl::parse(&format!("https://{}", arg))
+    };
+    match url {
+        Ok(url) => Some(url),
+        Err(error) => {
+            eprintln!("Invalid URL {}: {}", arg, error);
+            None
+        },
+    }
+}
+
+// Percent-encodes the path, so names with spaces or # still open. The path
+// must be absolute.
+fn file_url(path: &Path) -> Option<ServoUrl> {
+    Url::from_file_path(path).ok().map(ServoUrl::from_url)
+}
+
+// Returns the value of an argument of the form `--name=value`
+fn flag_value<'a>(arg: &'a str, name: &str) -> Option<&'a str> {
+    if arg.starts_with(name) && arg[name.len()..].starts_with('=') {
+        Some(&arg[name.len() + 1..])
+    } else {
+        None
+    }
+}
+
+// Parses sizes such as 1280x720
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.split('x');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Allowed URLs cover what's under their directory: https://example.com/app
+// allows https://example.com/app/page, but not https://example.com/apple
+fn allowed_url(entry: &str) -> Option<ServoUrl> {
+    match ServoUrl::parse(entry) {
+        Ok(url) => {
+            let mut url = url.as_url().clone();
+            if !url.path().ends_with('/') {
+                let path = format!("{}/", url.path());
+                url.set_path(&path);
+            }
+            Some(ServoUrl::from_url(url))
+        },
+        Err(error) => {
+            eprintln!("Invalid allowed URL {}: {}", entry, error);
+            None
+        },
+    }
+}
+
+// One URL per line, blank lines and lines starting with # are ignored
+fn parse_url_list(list: &str) -> Vec<ServoUrl> {
+    list.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match ServoUrl::parse(line) {
+            Ok(url) => Some(url),
+            Err(error) => {
+                eprintln!("Invalid URL {}: {}", line, error);
+                None
+            },
+        })
+        .collect()
+}
+
+fn read_file(path: &str) -> ::std::io::Result<String> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
@@ -0,0 +1,231 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gdk::{self, ScrollDirection, CONTROL_MASK};
+use gtk::{GLArea, GestureSwipe, Inhibit, WidgetExt, Window};
+use servo::Servo;
+use servo::compositing::windowing::{MouseWindowEvent, WindowEvent};
+use servo::euclid::{TypedPoint2D, TypedVector2D};
+use servo::msg::constellation_msg::TraversalDirection;
+use servo::script_traits::{MouseButton, TouchEventType};
+use servo::webrender_api::ScrollLocation;
+
+use motion::MotionCoalescer;
+use screenshot::{self, Area};
+use LINE_HEIGHT;
+use ServoWindow;
+use ZOOM_STEP;
+use traverse_history;
+use zoom_by;
+
+// How far (in pixels) the pointer can move between press and release for a click
+const CLICK_DISTANCE: f64 = 5.0;
+
+// Horizontal speed (in pixels per second) a swipe needs to go back or forward
+const SWIPE_VELOCITY: f64 = 1000.0;
+
+// Sends the mouse, wheel and touchpad input over the page to servo. Returns
+// where the pointer last was, for the key scrolls.
+pub fn connect(gl_area: &GLArea, gtk_window: &Window, swipe: &GestureSwipe, servo: &Rc<RefCell<Servo<ServoWindow>>>,
+               window: &Rc<ServoWindow>) -> Rc<RefCell<(f64, f64)>> {
+    let pointer = Rc::new(RefCell::new((0.0, 0.0)));
+    let motion = Rc::new(MotionCoalescer::new(servo.clone(), window.clone()));
+    {
+        let pointer = pointer.clone();
+        let motion = motion.clone();
+        gl_area.connect_motion_notify_event(move |_, event| {
+            let (x, y) = event.get_position();
+            *pointer.borrow_mut() = (x, y);
+            motion.moved(x, y);
+            Inhibit(false)
+        });
+    }
+
+    connect_scroll(gtk_window, servo, window, &pointer, &motion);
+    connect_history(gl_area, swipe, servo, window);
+    // The area selection keeps the buttons while it's active, so it's connected first
+    connect_area_selection(gl_area, window);
+    connect_buttons(gl_area, servo, window, &pointer, &motion);
+    pointer
+}
+
+// Ctrl+scroll zooms, anything else scrolls the page under the pointer
+fn connect_scroll(gtk_window: &Window, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>,
+                  pointer: &Rc<RefCell<(f64, f64)>>, motion: &Rc<MotionCoalescer>) {
+    let pointer = pointer.clone();
+    let servo = servo.clone();
+    let motion = motion.clone();
+    let window = window.clone();
+    // Touchpads send many small deltas, they are added up to whole steps
+    let zoom_scroll = Cell::new(0.0);
+    gtk_window.connect_scroll_event(move |_, event| {
+        motion.flush();
+        // In lines, positive values scroll up and left. Only smooth events
+        // (touchpads) have a delta, and it can be diagonal.
+        let (dx, dy) = match event.get_direction() {
+            ScrollDirection::Up => (0.0, 1.0),
+            ScrollDirection::Down => (0.0, -1.0),
+            ScrollDirection::Left => (1.0, 0.0),
+            ScrollDirection::Right => (-1.0, 0.0),
+            ScrollDirection::Smooth | _ => {
+                let (dx, dy) = event.get_delta();
+                (-dx, -dy)
+            },
+        };
+        if event.get_state().contains(CONTROL_MASK) {
+            let scrolled = zoom_scroll.get() + dy;
+            if scrolled >= 1.0 {
+                zoom_by(&servo, &window, ZOOM_STEP);
+                zoom_scroll.set(0.0);
+            } else if scrolled <= -1.0 {
+                zoom_by(&servo, &window, 1.0 / ZOOM_STEP);
+                zoom_scroll.set(0.0);
+            } else {
+                zoom_scroll.set(scrolled);
+            }
+            return Inhibit(true);
+        }
+        let delta = TypedVector2D::new(dx as f32 * LINE_HEIGHT, dy as f32 * LINE_HEIGHT);
+        let scroll_location = ScrollLocation::Delta(delta);
+        let pointer = window.device_point(*pointer.borrow());
+        let pointer = TypedPoint2D::new(pointer.x as i32, pointer.y as i32);
+        let event = WindowEvent::Scroll(scroll_location, pointer, TouchEventType::Move);
+        servo.borrow_mut().handle_events(vec![event]);
+        Inhibit(false)
+    });
+}
+
+// Swipes and the mouse's back and forward buttons go through the history
+fn connect_history(gl_area: &GLArea, swipe: &GestureSwipe, servo: &Rc<RefCell<Servo<ServoWindow>>>,
+                   window: &Rc<ServoWindow>) {
+    {
+        let servo = servo.clone();
+        let window = window.clone();
+        swipe.connect_swipe(move |_, velocity_x, velocity_y| {
+            if velocity_x.abs() < SWIPE_VELOCITY || velocity_x.abs() < 2.0 * velocity_y.abs() {
+                return;
+            }
+            // Swiping to the right brings the previous page back
+            let direction = if velocity_x > 0.0 {
+                TraversalDirection::Back(1)
+            } else {
+                TraversalDirection::Forward(1)
+            };
+            traverse_history(&servo, &window, direction);
+        });
+    }
+
+    // Mouse back and forward buttons
+    {
+        let servo = servo.clone();
+        let window = window.clone();
+        gl_area.connect_button_press_event(move |_, event| {
+            let direction = match event.get_button() {
+                8 => TraversalDirection::Back(1),
+                9 => TraversalDirection::Forward(1),
+                _ => return Inhibit(false),
+            };
+            traverse_history(&servo, &window, direction);
+            Inhibit(true)
+        });
+    }
+}
+
+// Dragging a rectangle over the page copies it, once Ctrl+Shift+A was pressed
+fn connect_area_selection(gl_area: &GLArea, window: &Rc<ServoWindow>) {
+    {
+        let area_selection = window.area_selection.clone();
+        gl_area.connect_button_press_event(move |_, event| {
+            if !area_selection.active.get() {
+                return Inhibit(false);
+            }
+            area_selection.start.set(Some(event.get_position()));
+            Inhibit(true)
+        });
+    }
+
+    {
+        let area_selection = window.area_selection.clone();
+        let window = window.clone();
+        gl_area.connect_button_release_event(move |_, event| {
+            if !area_selection.active.get() {
+                return Inhibit(false);
+            }
+            if let Some(start) = area_selection.start.get() {
+                area_selection.active.set(false);
+                area_selection.start.set(None);
+                window.set_cursor_name("default");
+                screenshot::copy_to_clipboard(&window, Some(Area::from_corners(start, event.get_position())));
+            }
+            Inhibit(true)
+        });
+    }
+}
+
+fn connect_buttons(gl_area: &GLArea, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>,
+                   pointer: &Rc<RefCell<(f64, f64)>>, motion: &Rc<MotionCoalescer>) {
+    let pressed: Rc<Cell<Option<(MouseButton, (f64, f64))>>> = Rc::new(Cell::new(None));
+    {
+        let pointer = pointer.clone();
+        let servo = servo.clone();
+        let motion = motion.clone();
+        let pressed = pressed.clone();
+        let window = window.clone();
+        gl_area.connect_button_press_event(move |_, event| {
+            // Double and triple clicks also send a plain press first
+            if event.get_event_type() != gdk::EventType::ButtonPress {
+                return Inhibit(false);
+            }
+            let button = match mouse_button(event.get_button()) {
+                Some(button) => button,
+                None => return Inhibit(false),
+            };
+            motion.flush();
+            let (x, y) = event.get_position();
+            *pointer.borrow_mut() = (x, y);
+            pressed.set(Some((button, (x, y))));
+            let event = MouseWindowEvent::MouseDown(button, window.device_point((x, y)));
+            servo.borrow_mut().handle_events(vec![WindowEvent::MouseWindowEventClass(event)]);
+            Inhibit(false)
+        });
+    }
+
+    {
+        let pointer = pointer.clone();
+        let servo = servo.clone();
+        let motion = motion.clone();
+        let window = window.clone();
+        gl_area.connect_button_release_event(move |_, event| {
+            let button = match mouse_button(event.get_button()) {
+                Some(button) => button,
+                None => return Inhibit(false),
+            };
+            motion.flush();
+            let (x, y) = event.get_position();
+            *pointer.borrow_mut() = (x, y);
+            let point = window.device_point((x, y));
+            let mut events = vec![WindowEvent::MouseWindowEventClass(MouseWindowEvent::MouseUp(button, point))];
+            if let Some((pressed_button, (press_x, press_y))) = pressed.get() {
+                pressed.set(None);
+                // In logical pixels, so a click is as hard to do on any screen
+                let distance = ((x - press_x).powi(2) + (y - press_y).powi(2)).sqrt();
+                if pressed_button == button && distance <= CLICK_DISTANCE {
+                    events.push(WindowEvent::MouseWindowEventClass(MouseWindowEvent::Click(button, point)));
+                }
+            }
+            servo.borrow_mut().handle_events(events);
+            Inhibit(false)
+        });
+    }
+}
+
+// GDK numbers buttons from 1, left to right. Back and forward are handled by
+// the embedder.
+fn mouse_button(button: u32) -> Option<MouseButton> {
+    match button {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        _ => None,
+    }
+}
@@ -0,0 +1,86 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk::{
+    Align,
+    BoxExt,
+    Button,
+    ButtonExt,
+    ContainerExt,
+    GLArea,
+    Label,
+    Overlay,
+    OverlayExt,
+    WidgetExt,
+};
+use gtk::Orientation::Vertical;
+
+// Consecutive failed frames before giving up and telling the user
+const MAX_FAILURES: u32 = 3;
+
+// Covers the page with a "rendering unavailable" notice when frames keep
+// failing, instead of leaving a blank or frozen GLArea
+pub struct RenderFallback {
+    overlay: Overlay,
+    notice: gtk::Box,
+    retry_button: Button,
+    failures: Rc<Cell<u32>>,
+}
+
+impl RenderFallback {
+    pub fn new(gl_area: &GLArea) -> RenderFallback {
+        let overlay = Overlay::new();
+        overlay.set_vexpand(true);
+        overlay.add(gl_area);
+
+        let notice = gtk::Box::new(Vertical, 12);
+        notice.set_halign(Align::Center);
+        notice.set_valign(Align::Center);
+        notice.pack_start(&Label::new(Some("Rendering unavailable")), false, false, 0);
+        let retry_button = Button::new_with_label("Retry rendering");
+        notice.pack_start(&retry_button, false, false, 0);
+        notice.show_all();
+        notice.set_no_show_all(true);
+        notice.hide();
+        overlay.add_overlay(&notice);
+
+        RenderFallback {
+            overlay,
+            notice,
+            retry_button,
+            failures: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn widget(&self) -> &Overlay {
+        &self.overlay
+    }
+
+    pub fn failed(&self, reason: &str) {
+        let failures = self.failures.get() + 1;
+        self.failures.set(failures);
+        eprintln!("Rendering failed ({} in a row): {}", failures, reason);
+        if failures == MAX_FAILURES {
+            self.notice.show();
+        }
+    }
+
+    // A transient failure doesn't leave the notice over a working page
+    pub fn succeeded(&self) {
+        self.failures.set(0);
+        if self.notice.get_visible() {
+            self.notice.hide();
+        }
+    }
+
+    // Called when the user asks to retry, after the notice is hidden
+    pub fn connect_retry<F: Fn() + 'static>(&self, retry: F) {
+        let notice = self.notice.clone();
+        let failures = self.failures.clone();
+        self.retry_button.connect_clicked(move |_| {
+            notice.hide();
+            failures.set(0);
+            retry();
+        });
+    }
+}
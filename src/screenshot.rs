@@ -0,0 +1,98 @@
+use std::cell::Cell;
+use std::io;
+
+use gdk;
+use gdk_pixbuf::{Colorspace, Pixbuf};
+use gtk::{Clipboard, GLAreaExt, WidgetExt};
+use image::{self, ColorType};
+use servo::gl;
+
+use ServoWindow;
+
+// A rectangle in GLArea coordinates (logical pixels, top-left origin)
+#[derive(Clone, Copy)]
+pub struct Area {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Area {
+    pub fn from_corners(a: (f64, f64), b: (f64, f64)) -> Area {
+        Area {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            width: (a.0 - b.0).abs(),
+            height: (a.1 - b.1).abs(),
+        }
+    }
+}
+
+// State of the "drag a rectangle to capture it" mode
+pub struct AreaSelection {
+    pub active: Cell<bool>,
+    pub start: Cell<Option<(f64, f64)>>,
+}
+
+impl AreaSelection {
+    pub fn new() -> AreaSelection {
+        AreaSelection {
+            active: Cell::new(false),
+            start: Cell::new(None),
+        }
+    }
+}
+
+// Reads the last composited frame, or part of it. Returns the size of the
+// captured area in device pixels and the RGBA rows, top row first.
+pub fn read_framebuffer(window: &ServoWindow, area: Option<Area>) -> (i32, i32, Vec<u8>) {
+    // Servo's device pixels are the GL buffer's
+    let scale = window.buffer_scale() as f64;
+    let frame_width = window.gl_area.get_allocated_width() as f64 * scale;
+    let frame_height = window.gl_area.get_allocated_height() as f64 * scale;
+    let area = area.unwrap_or(Area {
+        x: 0.0,
+        y: 0.0,
+        width: frame_width / scale,
+        height: frame_height / scale,
+    });
+
+    // Clamp to the frame, in device pixels
+    let x = (area.x * scale).max(0.0).min(frame_width);
+    let y = (area.y * scale).max(0.0).min(frame_height);
+    let width = (area.width * scale).min(frame_width - x).max(0.0) as i32;
+    let height = (area.height * scale).min(frame_height - y).max(0.0) as i32;
+
+    window.gl_area.make_current();
+    window.gl_area.attach_buffers();
+    // GL's origin is the bottom-left corner
+    let gl_y = frame_height as i32 - y as i32 - height;
+    let pixels = window.gl.read_pixels(x as i32, gl_y, width, height, gl::RGBA, gl::UNSIGNED_BYTE);
+
+    let stride = width as usize * 4;
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks(stride).rev() {
+        flipped.extend_from_slice(row);
+    }
+    (width, height, flipped)
+}
+
+pub fn copy_to_clipboard(window: &ServoWindow, area: Option<Area>) {
+    let (width, height, pixels) = read_framebuffer(window, area);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let pixbuf = Pixbuf::new_from_vec(pixels, Colorspace::Rgb, true, 8, width, height, width * 4);
+    let clipboard = Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+    clipboard.set_image(&pixbuf);
+}
+
+// The format follows the file's extension, PNG for .png
+pub fn save_to_file(window: &ServoWindow, path: &str) -> io::Result<()> {
+    let (width, height, pixels) = read_framebuffer(window, None);
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::Other, "nothing was rendered"));
+    }
+    image::save_buffer(path, &pixels, width as u32, height as u32, ColorType::RGBA(8))
+}
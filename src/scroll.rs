@@ -0,0 +1,128 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+use gtk::{self, Continue};
+use servo::Servo;
+use servo::compositing::windowing::WindowEvent;
+use servo::euclid::{TypedPoint2D, TypedVector2D};
+use servo::script_traits::TouchEventType;
+use servo::webrender_api::ScrollLocation;
+
+use FRAME_INTERVAL_MS;
+use ServoWindow;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease-out" => Some(Easing::EaseOut),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            _ => None,
+        }
+    }
+
+    // Maps the elapsed fraction of the animation (0..1) to the scrolled fraction (0..1)
+    fn apply(&self, t: f32) -> f32 {
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut =>
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                },
+        }
+    }
+}
+
+struct Animation {
+    start: Instant,
+    // Total distance to travel, and what has already been sent to servo
+    delta: (f32, f32),
+    emitted: (f32, f32),
+    pointer: (i32, i32),
+}
+
+// Scrolls performed by the embedder itself (keyboard, programmatic). Wheel
+// and trackpad scrolls are already smooth and are sent to servo as they come.
+pub struct SmoothScroller {
+    servo: Rc<RefCell<Servo<ServoWindow>>>,
+    duration_ms: u32,
+    easing: Easing,
+    animation: Rc<RefCell<Option<Animation>>>,
+}
+
+impl SmoothScroller {
+    pub fn new(servo: Rc<RefCell<Servo<ServoWindow>>>, duration_ms: u32, easing: Easing) -> SmoothScroller {
+        SmoothScroller {
+            servo,
+            duration_ms,
+            easing,
+            animation: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn scroll_by(&self, dx: f32, dy: f32, pointer: (i32, i32)) {
+        let mut animation = self.animation.borrow_mut();
+        if let Some(ref mut running) = *animation {
+            // Retarget the running animation: keep what's left to scroll and add the new delta
+            running.delta = (running.delta.0 - running.emitted.0 + dx,
+                             running.delta.1 - running.emitted.1 + dy);
+            running.emitted = (0.0, 0.0);
+            running.start = Instant::now();
+            running.pointer = pointer;
+            return;
+        }
+        *animation = Some(Animation {
+            start: Instant::now(),
+            delta: (dx, dy),
+            emitted: (0.0, 0.0),
+            pointer,
+        });
+
+        // Events are never sent synchronously, so scroll_by can be called while servo is borrowed
+        let interval = if self.duration_ms == 0 { 0 } else { FRAME_INTERVAL_MS };
+        let servo = self.servo.clone();
+        let animation = self.animation.clone();
+        let duration_ms = self.duration_ms;
+        let easing = self.easing;
+        gtk::timeout_add(interval, move || {
+            let (event, done) = {
+                let mut current = animation.borrow_mut();
+                let (event, done) = {
+                    let running = current.as_mut().unwrap();
+                    let progress = if duration_ms == 0 {
+                        1.0
+                    } else {
+                        let elapsed = running.start.elapsed();
+                        let elapsed_ms = elapsed.as_secs() as f32 * 1000.0 + elapsed.subsec_nanos() as f32 / 1_000_000.0;
+                        (elapsed_ms / duration_ms as f32).min(1.0)
+                    };
+                    let eased = easing.apply(progress);
+                    let target = (running.delta.0 * eased, running.delta.1 * eased);
+                    let step = (target.0 - running.emitted.0, target.1 - running.emitted.1);
+                    running.emitted = target;
+
+                    let scroll_location = ScrollLocation::Delta(TypedVector2D::new(step.0, step.1));
+                    let pointer = TypedPoint2D::new(running.pointer.0, running.pointer.1);
+                    (WindowEvent::Scroll(scroll_location, pointer, TouchEventType::Move), progress >= 1.0)
+                };
+                if done {
+                    *current = None;
+                }
+                (event, done)
+            };
+            servo.borrow_mut().handle_events(vec![event]);
+            Continue(!done)
+        });
+    }
+}
@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gdk::{CONTROL_MASK, MOD1_MASK};
+use gdk::enums::key;
+use gtk::{Inhibit, WidgetExt, WindowExt};
+use servo::Servo;
+use servo::compositing::windowing::{WebRenderDebugOption, WindowEvent};
+use servo::msg::constellation_msg::TraversalDirection;
+
+use edit;
+use memory;
+use options::Options;
+use screenshot;
+use ServoWindow;
+use TabActions;
+use Ui;
+use ZOOM_STEP;
+use reset_zoom;
+use traverse_history;
+use zoom_by;
+
+// Embedder shortcuts, handled before the focused widget gets the key
+pub fn connect(ui: &Ui, options: &Options, servo: &Rc<RefCell<Servo<ServoWindow>>>, window: &Rc<ServoWindow>,
+               tab_actions: TabActions) {
+    let servo = servo.clone();
+    let window = window.clone();
+    let editor = options.editor.clone();
+    let area_selection = window.area_selection.clone();
+    let memory_label = ui.memory_label.clone();
+    let kiosk = options.kiosk;
+    let theme = ui.theme.clone();
+    let tabs = ui.tabs.clone();
+    let url_bar = ui.url_bar.clone();
+    ui.gtk_window.connect_key_press_event(move |gtk_window, event| {
+        let control = event.get_state().contains(CONTROL_MASK);
+        let alt = event.get_state().contains(MOD1_MASK);
+        if kiosk {
+            // The only shortcut is a hidden exit combination (Ctrl+Alt+Shift+Q)
+            if event.get_keyval() == key::Q && control && alt {
+                gtk_window.close();
+                return Inhibit(true);
+            }
+            return Inhibit(false);
+        }
+        if let Some(ref url_bar) = url_bar {
+            match event.get_keyval() {
+                key::l if control => {
+                    url_bar.focus();
+                    return Inhibit(true);
+                },
+                // Typing an address must not trigger page shortcuts
+                _ if url_bar.has_focus() => return Inhibit(false),
+                _ => {},
+            }
+        }
+        match event.get_keyval() {
+            key::Left if alt => traverse_history(&servo, &window, TraversalDirection::Back(1)),
+            key::Right if alt => traverse_history(&servo, &window, TraversalDirection::Forward(1)),
+            key::r if control && alt => match theme {
+                Some(ref theme) => theme.reload(),
+                None => eprintln!("No theme to reload, use --theme"),
+            },
+            key::r if control => {
+                if let Some(browser_id) = window.selected_browser() {
+                    servo.borrow_mut().handle_events(vec![WindowEvent::Reload(browser_id)]);
+                }
+            },
+            key::F5 => {
+                if let Some(browser_id) = window.selected_browser() {
+                    servo.borrow_mut().handle_events(vec![WindowEvent::Reload(browser_id)]);
+                }
+            },
+            key::e if control => {
+                // Editing is only available for local pages
+                match window.current_url().and_then(|url| edit::local_path(&url)) {
+                    Some(path) => edit::open_in_editor(editor.as_ref().map(|e| e.as_str()), &path),
+                    None => eprintln!("Only local file:// pages can be edited"),
+                }
+            },
+            // With shift held, GDK reports the uppercase keyval
+            key::S if control => screenshot::copy_to_clipboard(&window, None),
+            key::A if control => {
+                area_selection.active.set(true);
+                window.set_cursor_name("crosshair");
+            },
+            key::t if control => (tab_actions.open)(),
+            key::P if control => {
+                if let (&Some(ref tabs), Some(browser_id)) = (&tabs, window.selected_browser()) {
+                    tabs.set_pinned(browser_id, !tabs.is_pinned(browser_id));
+                }
+            },
+            key::w if control => {
+                if let Some(browser_id) = window.selected_browser() {
+                    (tab_actions.close)(browser_id);
+                }
+            },
+            key::F12 if control => {
+                let event = WindowEvent::ToggleWebRenderDebug(WebRenderDebugOption::Profiler);
+                servo.borrow_mut().handle_events(vec![event]);
+            },
+            key::M if control => {
+                let visible = !memory_label.get_visible();
+                memory_label.set_visible(visible);
+                memory::refresh(&memory_label);
+            },
+            // Plus is shifted on most layouts, so Ctrl+= works as well
+            key::plus | key::equal | key::KP_Add if control => zoom_by(&servo, &window, ZOOM_STEP),
+            key::minus | key::KP_Subtract if control => zoom_by(&servo, &window, 1.0 / ZOOM_STEP),
+            key::_0 | key::KP_0 if control => {
+                if let Some(browser_id) = window.selected_browser() {
+                    reset_zoom(&servo, &window, browser_id);
+                }
+            },
+            key::Escape if area_selection.active.get() => {
+                area_selection.active.set(false);
+                area_selection.start.set(None);
+                window.set_cursor_name("default");
+            },
+            // Everything else goes to the page
+            _ => return Inhibit(false),
+        }
+        Inhibit(true)
+    });
+}
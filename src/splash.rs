@@ -0,0 +1,65 @@
+use std::cell::Cell;
+
+use gtk::{
+    Align,
+    BoxExt,
+    ContainerExt,
+    EventBox,
+    Image,
+    Spinner,
+    SpinnerExt,
+    WidgetExt,
+};
+use gtk::Orientation::Vertical;
+
+// Covers the GLArea from startup until the first page is painted, so a slow
+// start doesn't show a blank or garbage-filled area
+pub struct Splash {
+    event_box: EventBox,
+    spinner: Spinner,
+    // Set once the page has content, the next frame presented is a real one
+    content_expected: Cell<bool>,
+}
+
+impl Splash {
+    pub fn new() -> Splash {
+        let content = gtk::Box::new(Vertical, 12);
+        content.set_halign(Align::Center);
+        content.set_valign(Align::Center);
+        content.pack_start(&Image::new_from_file("resources/servo.png"), false, false, 0);
+        let spinner = Spinner::new();
+        spinner.start();
+        content.pack_start(&spinner, false, false, 0);
+
+        // Opaque, so the GLArea's uninitialized buffer never shows through
+        let event_box = EventBox::new();
+        event_box.add(&content);
+
+        Splash {
+            event_box,
+            spinner,
+            content_expected: Cell::new(false),
+        }
+    }
+
+    pub fn widget(&self) -> &EventBox {
+        &self.event_box
+    }
+
+    pub fn expect_content(&self) {
+        self.content_expected.set(true);
+    }
+
+    pub fn frame_presented(&self) {
+        if self.content_expected.get() {
+            self.dismiss();
+        }
+    }
+
+    pub fn dismiss(&self) {
+        if self.event_box.get_visible() {
+            self.spinner.stop();
+            self.event_box.hide();
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use gtk::{Align, ContainerExt, EventBox, Label, LabelExt, WidgetExt};
+
+// Shows the target of the hovered link in the page's bottom left corner.
+// It floats over the page so the page isn't resized on every hover.
+pub struct StatusBar {
+    event_box: EventBox,
+    label: Label,
+}
+
+impl StatusBar {
+    pub fn new() -> StatusBar {
+        let label = Label::new(None);
+        label.set_margin_start(4);
+        label.set_margin_end(4);
+        label.show();
+
+        // Unlike a plain label, an event box paints the theme's background
+        let event_box = EventBox::new();
+        event_box.set_halign(Align::Start);
+        event_box.set_valign(Align::End);
+        event_box.add(&label);
+        event_box.set_no_show_all(true);
+        if let Some(context) = event_box.get_style_context() {
+            context.add_class("status");
+        }
+
+        StatusBar {
+            event_box,
+            label,
+        }
+    }
+
+    pub fn widget(&self) -> &EventBox {
+        &self.event_box
+    }
+
+    pub fn set_text(&self, text: Option<&str>) {
+        match text {
+            Some(text) if !text.is_empty() => {
+                self.label.set_text(text);
+                self.event_box.show();
+            },
+            _ => self.event_box.hide(),
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gdk::{NotifyType, ENTER_NOTIFY_MASK, LEAVE_NOTIFY_MASK};
+use gtk::{
+    self,
+    BoxExt,
+    Button,
+    ButtonExt,
+    Cast,
+    ContainerExt,
+    EventBox,
+    EventBoxExt,
+    IconSize,
+    Image,
+    Inhibit,
+    Label,
+    LabelExt,
+    Notebook,
+    NotebookExt,
+    PackType,
+    ReliefStyle,
+    WidgetExt,
+};
+use gtk::Orientation::{Horizontal, Vertical};
+use pango::EllipsizeMode;
+use servo::BrowserId;
+
+use options::CloseButtons;
+
+// Room the notebook takes around each tab label (in pixels)
+const TAB_PADDING: i32 = 24;
+
+struct Tab {
+    browser_id: BrowserId,
+    // Empty: all browsers draw into the same GLArea, the notebook only
+    // provides the tab strip
+    page: gtk::Widget,
+    // Sized by TabWidths, and tells when the pointer is over the tab
+    label: EventBox,
+    // The title and zoom, replaced by the pin icon on pinned tabs
+    details: gtk::Box,
+    title: Label,
+    // Hidden at 100%, clicking it resets the zoom
+    zoom: Button,
+    pin_icon: Image,
+    close_button: Button,
+    pinned: Rc<Cell<bool>>,
+}
+
+// Tabs share the tab strip's width evenly, within these bounds
+struct TabWidths {
+    min: i32,
+    max: i32,
+    // What the tabs were last given
+    current: Cell<i32>,
+}
+
+impl TabWidths {
+    // Pinned tabs keep their icon's width, the others share what's left
+    fn fit(&self, notebook: &Notebook, new_tab_button: &Button, tabs: &[Tab]) {
+        let mut available = notebook.get_allocated_width() - new_tab_button.get_allocated_width();
+        let mut unpinned = 0;
+        for tab in tabs {
+            if tab.pinned.get() {
+                available -= tab.label.get_allocated_width() + TAB_PADDING;
+            } else {
+                unpinned += 1;
+            }
+        }
+        if unpinned == 0 {
+            return;
+        }
+        let width = (available / unpinned - TAB_PADDING).max(self.min).min(self.max);
+        if width == self.current.get() {
+            return;
+        }
+        self.current.set(width);
+        for tab in tabs.iter().filter(|tab| !tab.pinned.get()) {
+            tab.label.set_size_request(width, -1);
+        }
+    }
+}
+
+// A tab strip with a tab per browser, a close button on each tab and a
+// button opening new ones
+pub struct Tabs {
+    notebook: Notebook,
+    new_tab_button: Button,
+    tabs: Rc<RefCell<Vec<Tab>>>,
+    widths: Rc<TabWidths>,
+    close_buttons: CloseButtons,
+    on_close: Rc<RefCell<Option<Box<Fn(BrowserId)>>>>,
+    on_reset_zoom: Rc<RefCell<Option<Box<Fn(BrowserId)>>>>,
+}
+
+impl Tabs {
+    pub fn new(min_width: u32, max_width: u32, close_buttons: CloseButtons) -> Tabs {
+        let notebook = Notebook::new();
+        notebook.set_show_border(false);
+        notebook.set_scrollable(true);
+
+        let new_tab_button = Button::new_with_label("+");
+        new_tab_button.set_relief(ReliefStyle::None);
+        new_tab_button.set_tooltip_text(Some("New tab (Ctrl+T)"));
+        new_tab_button.show();
+        notebook.set_action_widget(&new_tab_button, PackType::End);
+
+        let tabs: Rc<RefCell<Vec<Tab>>> = Rc::new(RefCell::new(vec![]));
+        let widths = Rc::new(TabWidths {
+            min: min_width as i32,
+            max: max_width as i32,
+            current: Cell::new(max_width as i32),
+        });
+
+        // Tabs shrink as the window does
+        {
+            let tabs = tabs.clone();
+            let widths = widths.clone();
+            let new_tab_button = new_tab_button.clone();
+            notebook.connect_size_allocate(move |notebook, _| {
+                widths.fit(notebook, &new_tab_button, &tabs.borrow());
+            });
+        }
+
+        if close_buttons == CloseButtons::Active {
+            let tabs = tabs.clone();
+            notebook.connect_switch_page(move |_, page, _| {
+                for tab in tabs.borrow().iter() {
+                    tab.close_button.set_visible(!tab.pinned.get() && tab.page == *page);
+                }
+            });
+        }
+
+        // Dragging a tab can't take it across the edge of the pinned ones
+        {
+            let tabs = tabs.clone();
+            notebook.connect_page_reordered(move |notebook, page, position| {
+                let tabs = tabs.borrow();
+                let pinned = tabs.iter().find(|tab| tab.page == *page).map_or(false, |tab| tab.pinned.get());
+                let pinned_count = tabs.iter().filter(|tab| tab.pinned.get()).count() as u32;
+                if pinned && position >= pinned_count {
+                    notebook.reorder_child(page, Some(pinned_count - 1));
+                } else if !pinned && position < pinned_count {
+                    notebook.reorder_child(page, Some(pinned_count));
+                }
+            });
+        }
+
+        Tabs {
+            notebook,
+            new_tab_button,
+            tabs,
+            widths,
+            close_buttons,
+            on_close: Rc::new(RefCell::new(None)),
+            on_reset_zoom: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn widget(&self) -> &Notebook {
+        &self.notebook
+    }
+
+    pub fn count(&self) -> usize {
+        self.tabs.borrow().len()
+    }
+
+    // Adds a tab at the end and switches to it
+    pub fn add(&self, browser_id: BrowserId) {
+        self.append(browser_id);
+        self.select(browser_id);
+    }
+
+    // Adds a tab at the end, behind the current one. The notebook only
+    // switches to it if it's the first tab.
+    pub fn append(&self, browser_id: BrowserId) {
+        let page = gtk::Box::new(Vertical, 0).upcast::<gtk::Widget>();
+        page.show();
+
+        // The tab's width is set by TabWidths, the title only fills it
+        let title = Label::new(None);
+        title.set_ellipsize(EllipsizeMode::End);
+        title.set_max_width_chars(1);
+        let zoom = Button::new();
+        zoom.set_relief(ReliefStyle::None);
+        zoom.set_focus_on_click(false);
+        zoom.set_tooltip_text(Some("Reset zoom (Ctrl+0)"));
+        zoom.set_no_show_all(true);
+        {
+            let on_reset_zoom = self.on_reset_zoom.clone();
+            zoom.connect_clicked(move |_| {
+                if let Some(ref on_reset_zoom) = *on_reset_zoom.borrow() {
+                    on_reset_zoom(browser_id);
+                }
+            });
+        }
+        let close_button = Button::new_with_label("×");
+        close_button.set_relief(ReliefStyle::None);
+        close_button.set_focus_on_click(false);
+        // Shown by the hover and switch handlers otherwise
+        close_button.set_no_show_all(self.close_buttons != CloseButtons::Always);
+        {
+            let on_close = self.on_close.clone();
+            close_button.connect_clicked(move |_| {
+                if let Some(ref on_close) = *on_close.borrow() {
+                    on_close(browser_id);
+                }
+            });
+        }
+        let pin_icon = Image::new_from_icon_name("view-pin-symbolic", IconSize::Menu.into());
+        pin_icon.set_no_show_all(true);
+        let details = gtk::Box::new(Horizontal, 4);
+        details.pack_start(&title, true, true, 0);
+        details.pack_start(&zoom, false, false, 0);
+        let label_box = gtk::Box::new(Horizontal, 4);
+        label_box.pack_start(&pin_icon, false, false, 0);
+        label_box.pack_start(&details, true, true, 0);
+        label_box.pack_start(&close_button, false, false, 0);
+        let label = EventBox::new();
+        label.set_visible_window(false);
+        label.add(&label_box);
+        label.set_size_request(self.widths.current.get(), -1);
+        label.show_all();
+
+        let pinned = Rc::new(Cell::new(false));
+        if self.close_buttons == CloseButtons::Hover {
+            label.add_events((ENTER_NOTIFY_MASK | LEAVE_NOTIFY_MASK).bits() as i32);
+            {
+                let close_button = close_button.clone();
+                let pinned = pinned.clone();
+                label.connect_enter_notify_event(move |_, _| {
+                    if !pinned.get() {
+                        close_button.show();
+                    }
+                    Inhibit(false)
+                });
+            }
+            let close_button = close_button.clone();
+            label.connect_leave_notify_event(move |_, event| {
+                // Going over the close button only leaves for a child
+                if event.get_detail() != NotifyType::Inferior {
+                    close_button.hide();
+                }
+                Inhibit(false)
+            });
+        }
+
+        // Switching pages looks the tab up, it has to be known first
+        self.tabs.borrow_mut().push(Tab {
+            browser_id,
+            page: page.clone(),
+            label: label.clone(),
+            details,
+            title,
+            zoom,
+            pin_icon,
+            close_button,
+            pinned,
+        });
+        self.notebook.append_page(&page, Some(&label));
+        self.notebook.set_tab_reorderable(&page, true);
+        self.widths.fit(&self.notebook, &self.new_tab_button, &self.tabs.borrow());
+    }
+
+    // The notebook switches to a neighbouring tab if it was the current one
+    pub fn remove(&self, browser_id: BrowserId) {
+        let page = match self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id) {
+            Some(tab) => tab.page.clone(),
+            None => return,
+        };
+        let index = self.notebook.page_num(&page);
+        self.notebook.remove_page(index);
+        self.tabs.borrow_mut().retain(|tab| tab.browser_id != browser_id);
+        self.widths.fit(&self.notebook, &self.new_tab_button, &self.tabs.borrow());
+    }
+
+    pub fn select(&self, browser_id: BrowserId) {
+        let page = match self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id) {
+            Some(tab) => tab.page.clone(),
+            None => return,
+        };
+        let index = self.notebook.page_num(&page);
+        self.notebook.set_current_page(index);
+    }
+
+    // Where the tab is in the tab strip, from the left
+    pub fn position(&self, browser_id: BrowserId) -> Option<u32> {
+        let tabs = self.tabs.borrow();
+        tabs.iter().find(|tab| tab.browser_id == browser_id).and_then(|tab| self.notebook.page_num(&tab.page))
+    }
+
+    // Past the last tab, the tab is moved to the end
+    pub fn move_to(&self, browser_id: BrowserId, position: u32) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id) {
+            self.notebook.reorder_child(&tab.page, Some(position));
+        }
+    }
+
+    // The next tab, or the previous one for the last tab. Tabs can be
+    // dragged around, so this follows the notebook's order.
+    pub fn neighbour(&self, browser_id: BrowserId) -> Option<BrowserId> {
+        let tabs = self.tabs.borrow();
+        let page = match tabs.iter().find(|tab| tab.browser_id == browser_id) {
+            Some(tab) => &tab.page,
+            None => return None,
+        };
+        let index = match self.notebook.page_num(page) {
+            Some(index) => index,
+            None => return None,
+        };
+        let neighbour = if index + 1 < self.notebook.get_n_pages() {
+            self.notebook.get_nth_page(Some(index + 1))
+        } else if index > 0 {
+            self.notebook.get_nth_page(Some(index - 1))
+        } else {
+            None
+        };
+        neighbour.and_then(|page| tabs.iter().find(|tab| tab.page == page).map(|tab| tab.browser_id))
+    }
+
+    pub fn set_title(&self, browser_id: BrowserId, title: &str) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id) {
+            tab.title.set_text(title);
+            // Pinned tabs only show it there
+            tab.label.set_tooltip_text(Some(title));
+        }
+    }
+
+    pub fn is_pinned(&self, browser_id: BrowserId) -> bool {
+        self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id).map_or(false, |tab| tab.pinned.get())
+    }
+
+    // Pinned tabs only show an icon, stay left of the others and have no
+    // close button
+    pub fn set_pinned(&self, browser_id: BrowserId, pinned: bool) {
+        let tabs = self.tabs.borrow();
+        let tab = match tabs.iter().find(|tab| tab.browser_id == browser_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        if tab.pinned.get() == pinned {
+            return;
+        }
+        tab.pinned.set(pinned);
+        tab.details.set_visible(!pinned);
+        tab.pin_icon.set_visible(pinned);
+        let selected = self.notebook.get_current_page() == self.notebook.page_num(&tab.page);
+        tab.close_button.set_visible(!pinned && match self.close_buttons {
+            CloseButtons::Always => true,
+            CloseButtons::Hover => false,
+            CloseButtons::Active => selected,
+        });
+        if pinned {
+            tab.label.set_size_request(-1, -1);
+        } else {
+            tab.label.set_size_request(self.widths.current.get(), -1);
+        }
+        // Last of the pinned tabs, or first of the others
+        let pinned_count = tabs.iter().filter(|tab| tab.pinned.get()).count() as u32;
+        let position = if pinned { pinned_count - 1 } else { pinned_count };
+        self.notebook.reorder_child(&tab.page, Some(position));
+        self.widths.fit(&self.notebook, &self.new_tab_button, &tabs);
+    }
+
+    // Shows the zoom as a percentage, unless it's 100%
+    pub fn set_zoom(&self, browser_id: BrowserId, zoom: f32) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|tab| tab.browser_id == browser_id) {
+            let percent = (zoom * 100.0).round() as i32;
+            tab.zoom.set_label(&format!("({}%)", percent));
+            tab.zoom.set_visible(percent != 100);
+        }
+    }
+
+    // Called with the browser of the newly selected tab
+    pub fn connect_switch<F: Fn(BrowserId) + 'static>(&self, switch: F) {
+        let tabs = self.tabs.clone();
+        self.notebook.connect_switch_page(move |_, page, _| {
+            let browser_id = tabs.borrow().iter().find(|tab| tab.page == *page).map(|tab| tab.browser_id);
+            if let Some(browser_id) = browser_id {
+                switch(browser_id);
+            }
+        });
+    }
+
+    pub fn connect_close<F: Fn(BrowserId) + 'static>(&self, close: F) {
+        *self.on_close.borrow_mut() = Some(Box::new(close));
+    }
+
+    pub fn connect_reset_zoom<F: Fn(BrowserId) + 'static>(&self, reset_zoom: F) {
+        *self.on_reset_zoom.borrow_mut() = Some(Box::new(reset_zoom));
+    }
+
+    pub fn connect_new_tab<F: Fn() + 'static>(&self, new_tab: F) {
+        self.new_tab_button.connect_clicked(move |_| new_tab());
+    }
+}
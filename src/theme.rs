@@ -0,0 +1,34 @@
+use gdk::Screen;
+use gtk::{self, CssProvider, StyleContext};
+
+// Styles the embedder's own widgets with a GTK CSS file. A bare name picks a
+// built-in theme from resources/themes.
+pub struct Theme {
+    provider: CssProvider,
+    path: String,
+}
+
+impl Theme {
+    pub fn new(name_or_path: &str) -> Theme {
+        let path = if name_or_path.contains('/') || name_or_path.ends_with(".css") {
+            name_or_path.to_owned()
+        } else {
+            format!("resources/themes/{}.css", name_or_path)
+        };
+        let provider = CssProvider::new();
+        match Screen::get_default() {
+            Some(screen) => StyleContext::add_provider_for_screen(&screen, &provider, gtk::STYLE_PROVIDER_PRIORITY_USER),
+            None => eprintln!("No screen to apply the theme to"),
+        }
+        let theme = Theme { provider, path };
+        theme.reload();
+        theme
+    }
+
+    // Reads the file again, so a theme can be tweaked while the browser runs
+    pub fn reload(&self) {
+        if let Err(error) = self.provider.load_from_path(&self.path) {
+            eprintln!("Can't load theme {}: {}", self.path, error);
+        }
+    }
+}
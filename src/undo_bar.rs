@@ -0,0 +1,84 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk::{
+    self,
+    Align,
+    BoxExt,
+    Cast,
+    Continue,
+    InfoBar,
+    InfoBarExt,
+    Label,
+    LabelExt,
+    MessageType,
+    ResponseType,
+    WidgetExt,
+};
+use pango::EllipsizeMode;
+
+// A bar offering to reopen the last closed tab, hidden again after a few
+// seconds. Closing another tab replaces the offer.
+pub struct UndoBar {
+    info_bar: InfoBar,
+    label: Label,
+    seconds: u32,
+    // Bumped whenever the bar is shown, so the timeout of an earlier offer
+    // doesn't hide it
+    shown: Rc<Cell<u32>>,
+}
+
+impl UndoBar {
+    pub fn new(seconds: u32) -> UndoBar {
+        let info_bar = InfoBar::new();
+        info_bar.set_message_type(MessageType::Info);
+        info_bar.set_show_close_button(true);
+        info_bar.set_no_show_all(true);
+        info_bar.add_button("Undo", ResponseType::Accept.into());
+
+        let label = Label::new(None);
+        label.set_halign(Align::Start);
+        label.set_ellipsize(EllipsizeMode::End);
+        label.show();
+        let content_area = info_bar.get_content_area().unwrap().downcast::<gtk::Box>().unwrap();
+        content_area.pack_start(&label, true, true, 0);
+
+        // Undoing or not, the offer is gone once answered
+        info_bar.connect_response(|info_bar, _| info_bar.hide());
+
+        UndoBar {
+            info_bar,
+            label,
+            seconds,
+            shown: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn widget(&self) -> &InfoBar {
+        &self.info_bar
+    }
+
+    pub fn offer(&self, text: &str) {
+        self.label.set_text(text);
+        self.info_bar.show();
+        let shown = self.shown.get().wrapping_add(1);
+        self.shown.set(shown);
+        let info_bar = self.info_bar.clone();
+        let current = self.shown.clone();
+        gtk::timeout_add_seconds(self.seconds, move || {
+            if current.get() == shown {
+                info_bar.hide();
+            }
+            Continue(false)
+        });
+    }
+
+    pub fn connect_undo<F: Fn() + 'static>(&self, undo: F) {
+        let undo_response: i32 = ResponseType::Accept.into();
+        self.info_bar.connect_response(move |_, response| {
+            if response == undo_response {
+                undo();
+            }
+        });
+    }
+}
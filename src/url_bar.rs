@@ -0,0 +1,85 @@
+use gtk::{BoxExt, EditableSignals, Entry, EntryExt, Spinner, SpinnerExt, WidgetExt};
+use gtk::Orientation::Horizontal;
+use servo::servo_url::ServoUrl;
+
+// Style class of an entry holding text that isn't a URL
+const INVALID_CLASS: &str = "error";
+
+// An address bar showing the current URL and loading what's typed in it,
+// with a spinner while the page loads
+pub struct UrlBar {
+    hbox: gtk::Box,
+    entry: Entry,
+    spinner: Spinner,
+}
+
+impl UrlBar {
+    pub fn new() -> UrlBar {
+        let entry = Entry::new();
+        entry.set_placeholder_text("Enter a URL");
+        // Editing clears the invalid state
+        entry.connect_changed(|entry| set_invalid(entry, false));
+
+        let spinner = Spinner::new();
+        let hbox = gtk::Box::new(Horizontal, 4);
+        hbox.pack_start(&entry, true, true, 0);
+        hbox.pack_start(&spinner, false, false, 4);
+
+        UrlBar {
+            hbox,
+            entry,
+            spinner,
+        }
+    }
+
+    pub fn widget(&self) -> &gtk::Box {
+        &self.hbox
+    }
+
+    pub fn set_loading(&self, loading: bool) {
+        if loading {
+            self.spinner.start();
+        } else {
+            self.spinner.stop();
+        }
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.entry.has_focus()
+    }
+
+    pub fn focus(&self) {
+        self.entry.grab_focus();
+    }
+
+    // Left alone while the user is typing
+    pub fn set_url(&self, url: &ServoUrl) {
+        if !self.entry.has_focus() {
+            self.entry.set_text(url.as_str());
+        }
+    }
+
+    // Called with the parsed URL when Enter is pressed
+    pub fn connect_load<F: Fn(ServoUrl) + 'static>(&self, load: F) {
+        self.entry.connect_activate(move |entry| {
+            let text = entry.get_text().unwrap_or_default();
+            match ServoUrl::parse(text.trim()) {
+                Ok(url) => load(url),
+                Err(error) => {
+                    eprintln!("Invalid URL {}: {}", text, error);
+                    set_invalid(entry, true);
+                },
+            }
+        });
+    }
+}
+
+fn set_invalid(entry: &Entry, invalid: bool) {
+    if let Some(context) = entry.get_style_context() {
+        if invalid {
+            context.add_class(INVALID_CLASS);
+        } else {
+            context.remove_class(INVALID_CLASS);
+        }
+    }
+}